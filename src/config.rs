@@ -1,75 +1,730 @@
+use std;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::prelude::*;
 
+use serde::{Deserialize, Deserializer};
 use toml;
 
-#[derive(Deserialize)]
+use inventory;
+use migration;
+use profile::Profile;
+use validation::{self, ConfigIssue};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    pub system: System,
+    pub kubernetes: Kubernetes,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub feature_gates: BTreeMap<String, bool>,
+    pub images: Images,
     pub bootstrap: Bootstrap,
     pub certificates: Certificates,
     pub secrets: Secrets,
     pub kubelet: Kubelet,
+    pub apiserver: ComponentConfig,
+    pub audit: Audit,
+    pub encryption: Encryption,
+    pub auth: Auth,
+    pub controller_manager: ComponentConfig,
+    pub scheduler: ComponentConfig,
+    pub proxy: ComponentConfig,
     pub etcd: Etcd,
     pub net: Net,
+    pub dns: Dns,
+    pub addons: Addons,
+    pub manifests: Manifests,
     pub kubeception: Kubeception,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub patches: Vec<Patch>,
+    #[serde(skip)]
+    pub source: String,
+    #[serde(skip)]
+    pub digest: String,
+    #[serde(skip)]
+    pub output_dir: Option<PathBuf>,
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct System {
+    pub profile: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Kubernetes {
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Images {
+    pub prefix: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pull_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyperkube: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flannel: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flannel_cni: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coredns: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kube_dns: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dnsmasq_nanny: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_sidecar: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calico_node: Option<ImageOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calico_cni: Option<ImageOverride>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ImageOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Bootstrap {
     pub manifests_path: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Certificates {
     pub ca_path: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Secrets {
     pub path: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Kubelet {
-    pub config_file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_args: BTreeMap<String, String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ComponentConfig {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_args: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Etcd {
-    pub config_file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
     pub config_path: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Net {
     pub cluster_cidr: String,
     pub service_cluster_ip_range: String,
     pub apiserver_cluster_ip: String,
     pub dns_cluster_ip: String,
+    pub cni: Cni,
+    pub flannel: Flannel,
+    pub calico: Calico,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Cni {
+    Flannel,
+    Calico,
+    Canal,
+    None,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dns {
+    pub provider: DnsProvider,
+    pub domain: String,
+    pub upstream_servers: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub stub_domains: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum DnsProvider {
+    #[serde(rename = "coredns")]
+    CoreDns,
+    #[serde(rename = "kube-dns")]
+    KubeDns,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Audit {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_file: Option<String>,
+    pub log_dir: String,
+    pub max_age: u32,
+    pub max_backup: u32,
+    pub max_size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_config_file: Option<String>,
+    pub webhook_mode: AuditWebhookMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditWebhookMode {
+    Batch,
+    Blocking,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Encryption {
+    pub enabled: bool,
+    pub provider: EncryptionProvider,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionProvider {
+    Aescbc,
+    Secretbox,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Auth {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oidc: Option<Oidc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Oidc {
+    pub issuer_url: String,
+    pub client_id: String,
+    #[serde(default = "Oidc::default_username_claim")]
+    pub username_claim: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups_claim: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Addons {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Manifests {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_dir: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Patch {
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    pub name: String,
+    #[serde(rename = "type", default = "PatchType::default")]
+    pub patch_type: PatchType,
+    pub patch: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchType {
+    Strategic,
+    Merge,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FlannelBackend {
+    Vxlan,
+    HostGw,
+    Wireguard,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Flannel {
+    pub backend: FlannelBackend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iface: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iface_regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u32>,
+    pub vxlan_port: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpipMode {
+    Always,
+    CrossSubnet,
+    Never,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calico {
+    pub mtu: u32,
+    pub ipip_mode: IpipMode,
+    pub ip_autodetection_method: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Kubeception {
     pub image: String,
+    #[serde(deserialize_with = "deserialize_nodeport")]
     pub nodeport: String,
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NodePort {
+    Number(i64),
+    Text(String),
+}
+
+fn deserialize_nodeport<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match NodePort::deserialize(deserializer)? {
+        NodePort::Number(nodeport) => Ok(nodeport.to_string()),
+        NodePort::Text(nodeport) => Ok(nodeport),
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            api_version: migration::API_VERSION.to_string(),
+            system: System::default(),
+            kubernetes: Kubernetes::default(),
+            feature_gates: BTreeMap::new(),
+            images: Images::default(),
+            bootstrap: Bootstrap::default(),
+            certificates: Certificates::default(),
+            secrets: Secrets::default(),
+            kubelet: Kubelet::default(),
+            apiserver: ComponentConfig::default(),
+            audit: Audit::default(),
+            encryption: Encryption::default(),
+            auth: Auth::default(),
+            controller_manager: ComponentConfig::default(),
+            scheduler: ComponentConfig::default(),
+            proxy: ComponentConfig::default(),
+            etcd: Etcd::default(),
+            net: Net::default(),
+            dns: Dns::default(),
+            addons: Addons::default(),
+            manifests: Manifests::default(),
+            kubeception: Kubeception::default(),
+            patches: Vec::new(),
+            source: String::new(),
+            digest: String::new(),
+            output_dir: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Default for System {
+    fn default() -> System {
+        System { profile: "kubic".to_string() }
+    }
+}
+
+impl Default for Kubernetes {
+    fn default() -> Kubernetes {
+        Kubernetes { version: "v1.11.0".to_string() }
+    }
+}
+
+impl Default for Images {
+    fn default() -> Images {
+        Images {
+            prefix: "k8s.gcr.io".to_string(),
+            registry: None,
+            pull_secret: None,
+            hyperkube: None,
+            pause: None,
+            flannel: None,
+            flannel_cni: None,
+            coredns: None,
+            kube_dns: None,
+            dnsmasq_nanny: None,
+            dns_sidecar: None,
+            calico_node: None,
+            calico_cni: None,
+        }
+    }
+}
+
+impl Images {
+    pub fn component(&self, name: &str) -> Option<&ImageOverride> {
+        match name {
+            "hyperkube" => self.hyperkube.as_ref(),
+            "pause" => self.pause.as_ref(),
+            "flannel" => self.flannel.as_ref(),
+            "flannel_cni" => self.flannel_cni.as_ref(),
+            "coredns" => self.coredns.as_ref(),
+            "kube_dns" => self.kube_dns.as_ref(),
+            "dnsmasq_nanny" => self.dnsmasq_nanny.as_ref(),
+            "dns_sidecar" => self.dns_sidecar.as_ref(),
+            "calico_node" => self.calico_node.as_ref(),
+            "calico_cni" => self.calico_cni.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Bootstrap {
+    fn default() -> Bootstrap {
+        Bootstrap { manifests_path: "/etc/kubernetes/manifests".to_string() }
+    }
+}
+
+impl Default for Certificates {
+    fn default() -> Certificates {
+        Certificates { ca_path: "/etc/ca-certificates".to_string() }
+    }
+}
+
+impl Default for Secrets {
+    fn default() -> Secrets {
+        Secrets { path: "/etc/kubernetes/bootstrap-secrets".to_string() }
+    }
+}
+
+impl Default for Etcd {
+    fn default() -> Etcd {
+        Etcd {
+            config_file: None,
+            config_path: "/etc/etcd".to_string(),
+        }
+    }
+}
+
+impl Default for Net {
+    fn default() -> Net {
+        Net {
+            cluster_cidr: "10.2.0.0/16".to_string(),
+            service_cluster_ip_range: "10.3.0.0/24".to_string(),
+            apiserver_cluster_ip: "10.3.0.1".to_string(),
+            dns_cluster_ip: "10.3.0.10".to_string(),
+            cni: Cni::Flannel,
+            flannel: Flannel::default(),
+            calico: Calico::default(),
+        }
+    }
+}
+
+impl Default for Flannel {
+    fn default() -> Flannel {
+        Flannel {
+            backend: FlannelBackend::Vxlan,
+            iface: None,
+            iface_regex: None,
+            mtu: None,
+            vxlan_port: 4789,
+        }
+    }
+}
+
+impl Default for Dns {
+    fn default() -> Dns {
+        Dns {
+            provider: DnsProvider::CoreDns,
+            domain: "cluster.local".to_string(),
+            upstream_servers: Vec::new(),
+            stub_domains: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Audit {
+    fn default() -> Audit {
+        Audit {
+            enabled: false,
+            policy_file: None,
+            log_dir: "/var/log/kubernetes/audit".to_string(),
+            max_age: 30,
+            max_backup: 10,
+            max_size: 100,
+            webhook_config_file: None,
+            webhook_mode: AuditWebhookMode::Batch,
+        }
+    }
+}
+
+impl Default for Encryption {
+    fn default() -> Encryption {
+        Encryption {
+            enabled: false,
+            provider: EncryptionProvider::Aescbc,
+        }
+    }
+}
+
+impl EncryptionProvider {
+    pub fn apiserver_value(&self) -> &'static str {
+        match *self {
+            EncryptionProvider::Aescbc => "aescbc",
+            EncryptionProvider::Secretbox => "secretbox",
+        }
+    }
+}
+
+impl Oidc {
+    fn default_username_claim() -> String {
+        "sub".to_string()
+    }
+}
+
+impl AuditWebhookMode {
+    pub fn apiserver_value(&self) -> &'static str {
+        match *self {
+            AuditWebhookMode::Batch => "batch",
+            AuditWebhookMode::Blocking => "blocking",
+        }
+    }
+}
+
+impl PatchType {
+    fn default() -> PatchType {
+        PatchType::Strategic
+    }
+}
+
+impl FlannelBackend {
+    pub fn flannel_value(&self) -> &'static str {
+        match *self {
+            FlannelBackend::Vxlan => "vxlan",
+            FlannelBackend::HostGw => "host-gw",
+            FlannelBackend::Wireguard => "wireguard",
+        }
+    }
+}
+
+impl Default for Calico {
+    fn default() -> Calico {
+        Calico {
+            mtu: 1440,
+            ipip_mode: IpipMode::Always,
+            ip_autodetection_method: "first-found".to_string(),
+        }
+    }
+}
+
+impl IpipMode {
+    pub fn calico_value(&self) -> &'static str {
+        match *self {
+            IpipMode::Always => "Always",
+            IpipMode::CrossSubnet => "CrossSubnet",
+            IpipMode::Never => "Never",
+        }
+    }
+}
+
+impl Default for Kubeception {
+    fn default() -> Kubeception {
+        Kubeception {
+            image: "ereslibre/kubeception:latest".to_string(),
+            nodeport: "30000".to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct JoinConfig {
     pub kubeconfig: String,
 }
 
+pub enum ConfigError {
+    ReadError(String),
+    ParseError(String),
+    VersionError(String),
+    ProfileError(String),
+    ValidationError(Vec<ConfigIssue>),
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(error: std::io::Error) -> ConfigError {
+        ConfigError::ReadError(error.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(error: toml::ser::Error) -> ConfigError {
+        ConfigError::ParseError(error.to_string())
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> ConfigError {
+        ConfigError::ParseError(error.to_string())
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::ReadError(ref error) => {
+                write!(f, "could not read configuration file: {}", error)
+            }
+            ConfigError::ParseError(ref error) => {
+                write!(f, "could not deserialize configuration file: {}", error)
+            }
+            ConfigError::VersionError(ref error) => {
+                write!(f, "could not migrate configuration file: {}", error)
+            }
+            ConfigError::ProfileError(ref profile) => {
+                write!(
+                    f,
+                    "unknown profile {}; known profiles are {}",
+                    profile,
+                    Profile::names().join(", ")
+                )
+            }
+            ConfigError::ValidationError(ref issues) => {
+                write!(f, "invalid configuration file:")?;
+                for issue in issues {
+                    write!(f, "\n  {}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ConfigError")
+    }
+}
+
 impl Config {
-    pub fn from_file<T>(path: T) -> Config
+    pub fn dry_run(&self) -> bool {
+        self.output_dir.is_some()
+    }
+
+    pub fn profile(&self) -> Result<&'static Profile, ConfigError> {
+        Profile::from_name(&self.system.profile).ok_or_else(|| {
+            ConfigError::ProfileError(self.system.profile.clone())
+        })
+    }
+
+    pub fn kubelet_config_file(&self) -> Result<String, ConfigError> {
+        match self.kubelet.config_file {
+            Some(ref config_file) => Ok(config_file.clone()),
+            None => Ok(self.profile()?.kubelet_config_file.to_string()),
+        }
+    }
+
+    pub fn etcd_config_file(&self) -> Result<String, ConfigError> {
+        match self.etcd.config_file {
+            Some(ref config_file) => Ok(config_file.clone()),
+            None => Ok(self.profile()?.etcd_config_file.to_string()),
+        }
+    }
+
+    pub fn host_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        match self.output_dir {
+            Some(ref output_dir) => {
+                match path.as_ref().strip_prefix("/") {
+                    Ok(relative_path) => output_dir.join(relative_path),
+                    Err(_) => output_dir.join(path),
+                }
+            }
+            None => path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn from_file<T>(path: T) -> Result<Config, ConfigError>
     where
         T: Into<PathBuf>,
     {
-        let mut file = File::open(path.into()).expect("configuration file not found");
+        let mut file = File::open(path.into())?;
         let mut contents = String::new();
-        file.read_to_string(&mut contents).expect(
-            "could not read configuration file",
-        );
-        toml::from_str(&contents).expect("could not deserialize configuration file")
+        file.read_to_string(&mut contents)?;
+        let (value, warnings) = Config::migrated_value(&contents)?;
+        let mut config: Config = value.try_into()?;
+        config.warnings = warnings;
+        config.warnings.extend(validation::warnings(&config));
+        config.digest = inventory::sha256(contents.as_bytes());
+        config.source = contents;
+        Ok(config)
+    }
+
+    pub fn migrated_value(contents: &str) -> Result<(toml::Value, Vec<String>), ConfigError> {
+        let mut value: toml::Value = toml::from_str(contents)?;
+        let warnings = migration::migrate(&mut value)?;
+        Ok((value, warnings))
+    }
+
+    pub fn migrate<T, U>(from: T, to: U) -> Result<Vec<String>, ConfigError>
+    where
+        T: AsRef<Path>,
+        U: AsRef<Path>,
+    {
+        let (value, warnings) = Config::migrated_value(&fs::read_to_string(from)?)?;
+        value.clone().try_into::<Config>()?;
+        fs::write(to, toml::to_string(&value)?)?;
+        Ok(warnings)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let issues = validation::validate(self);
+        if issues.is_empty() {
+            return Ok(());
+        }
+        Err(ConfigError::ValidationError(issues))
     }
 }
@@ -0,0 +1,249 @@
+use base64;
+
+use std;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use openssl;
+use openssl::rand::rand_bytes;
+use serde_json;
+
+use config::{Config, EncryptionProvider};
+use inventory::{FileKind, Inventory, InventoryError};
+
+pub const ENCRYPTION_CONFIG: &'static str = "encryption-config.yaml";
+const ENCRYPTION_KEYS: &'static str = "encryption-keys.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncryptionKey {
+    pub name: String,
+    pub provider: EncryptionProvider,
+    pub secret: String,
+}
+
+pub enum EncryptionError {
+    UnknownError,
+}
+
+impl From<std::io::Error> for EncryptionError {
+    fn from(_error: std::io::Error) -> EncryptionError {
+        EncryptionError::UnknownError
+    }
+}
+
+impl From<serde_json::Error> for EncryptionError {
+    fn from(_error: serde_json::Error) -> EncryptionError {
+        EncryptionError::UnknownError
+    }
+}
+
+impl From<openssl::error::ErrorStack> for EncryptionError {
+    fn from(_error: openssl::error::ErrorStack) -> EncryptionError {
+        EncryptionError::UnknownError
+    }
+}
+
+impl From<InventoryError> for EncryptionError {
+    fn from(_error: InventoryError) -> EncryptionError {
+        EncryptionError::UnknownError
+    }
+}
+
+impl fmt::Debug for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EncryptionError")
+    }
+}
+
+fn keys_path(config: &Config) -> PathBuf {
+    PathBuf::from(&config.secrets.path).join(ENCRYPTION_KEYS)
+}
+
+pub fn config_path(config: &Config) -> PathBuf {
+    PathBuf::from(&config.secrets.path).join(ENCRYPTION_CONFIG)
+}
+
+pub fn load(config: &Config) -> Result<Vec<EncryptionKey>, EncryptionError> {
+    match fs::read(config.host_path(keys_path(config))) {
+        Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+        Err(error) => {
+            error!(
+                "could not read {}; run the certificates phase to generate it",
+                keys_path(config).display()
+            );
+            Err(error.into())
+        }
+    }
+}
+
+pub fn save(config: &Config, keys: &[EncryptionKey]) -> Result<(), EncryptionError> {
+    Inventory::write_file(
+        config,
+        keys_path(config),
+        FileKind::Pki,
+        serde_json::to_string_pretty(keys)?.as_bytes(),
+    )?;
+    Ok(())
+}
+
+pub fn exists(config: &Config) -> bool {
+    config.host_path(keys_path(config)).exists()
+}
+
+pub fn present(config: &Config) -> Result<(), EncryptionError> {
+    if exists(config) {
+        return Ok(());
+    }
+    let key = generate(config, &[])?;
+    save(config, &[key])
+}
+
+pub fn generate(config: &Config, keys: &[EncryptionKey]) -> Result<EncryptionKey, EncryptionError> {
+    let index = keys.iter()
+        .filter_map(|key| key.name.trim_left_matches("key-").parse::<u32>().ok())
+        .max()
+        .unwrap_or(0) + 1;
+    let mut secret = [0u8; 32];
+    rand_bytes(&mut secret)?;
+    Ok(EncryptionKey {
+        name: format!("key-{}", index),
+        provider: config.encryption.provider,
+        secret: base64::encode(&secret),
+    })
+}
+
+pub fn provider_config_flag(config: &Config) -> &'static str {
+    if supports_encryption_configuration(config) {
+        "--encryption-provider-config"
+    } else {
+        "--experimental-encryption-provider-config"
+    }
+}
+
+pub fn configuration(config: &Config, keys: &[EncryptionKey]) -> String {
+    let mut contents = if supports_encryption_configuration(config) {
+        String::from("apiVersion: apiserver.config.k8s.io/v1\nkind: EncryptionConfiguration\n")
+    } else {
+        String::from("apiVersion: v1\nkind: EncryptionConfig\n")
+    };
+    contents.push_str("resources:\n- resources:\n  - secrets\n  providers:\n");
+    let mut providers: Vec<EncryptionProvider> = Vec::new();
+    for key in keys {
+        if !providers.contains(&key.provider) {
+            providers.push(key.provider);
+        }
+    }
+    for provider in providers {
+        contents.push_str(&format!("  - {}:\n      keys:\n", provider.apiserver_value()));
+        for key in keys.iter().filter(|key| key.provider == provider) {
+            contents.push_str(&format!(
+                "      - name: {}\n        secret: {}\n",
+                key.name,
+                key.secret
+            ));
+        }
+    }
+    contents.push_str("  - identity: {}\n");
+    contents
+}
+
+fn supports_encryption_configuration(config: &Config) -> bool {
+    let version = config.kubernetes.version.trim_left_matches('v');
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    major > 1 || (major == 1 && minor >= 13)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use toml;
+
+    fn key(name: &str, provider: EncryptionProvider) -> EncryptionKey {
+        EncryptionKey {
+            name: name.to_string(),
+            provider: provider,
+            secret: format!("{}-secret", name),
+        }
+    }
+
+    #[test]
+    fn keys_are_grouped_by_provider_before_identity() {
+        let config: Config = toml::from_str("[kubernetes]\nversion = \"v1.13.1\"\n").unwrap();
+        let keys = [
+            key("key-3", EncryptionProvider::Secretbox),
+            key("key-2", EncryptionProvider::Aescbc),
+            key("key-1", EncryptionProvider::Aescbc),
+        ];
+        assert_eq!(
+            configuration(&config, &keys),
+            concat!(
+                "apiVersion: apiserver.config.k8s.io/v1\n",
+                "kind: EncryptionConfiguration\n",
+                "resources:\n",
+                "- resources:\n",
+                "  - secrets\n",
+                "  providers:\n",
+                "  - secretbox:\n",
+                "      keys:\n",
+                "      - name: key-3\n",
+                "        secret: key-3-secret\n",
+                "  - aescbc:\n",
+                "      keys:\n",
+                "      - name: key-2\n",
+                "        secret: key-2-secret\n",
+                "      - name: key-1\n",
+                "        secret: key-1-secret\n",
+                "  - identity: {}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn interleaved_providers_are_listed_once() {
+        let config: Config = toml::from_str("[kubernetes]\nversion = \"v1.13.1\"\n").unwrap();
+        let keys = [
+            key("key-3", EncryptionProvider::Secretbox),
+            key("key-2", EncryptionProvider::Aescbc),
+            key("key-1", EncryptionProvider::Secretbox),
+        ];
+        assert!(configuration(&config, &keys).ends_with(concat!(
+            "  providers:\n",
+            "  - secretbox:\n",
+            "      keys:\n",
+            "      - name: key-3\n",
+            "        secret: key-3-secret\n",
+            "      - name: key-1\n",
+            "        secret: key-1-secret\n",
+            "  - aescbc:\n",
+            "      keys:\n",
+            "      - name: key-2\n",
+            "        secret: key-2-secret\n",
+            "  - identity: {}\n",
+        )));
+    }
+
+    #[test]
+    fn older_versions_use_the_experimental_configuration() {
+        let config: Config = toml::from_str("[kubernetes]\nversion = \"v1.12.3\"\n").unwrap();
+        let contents = configuration(&config, &[key("key-1", EncryptionProvider::Aescbc)]);
+        assert!(contents.starts_with("apiVersion: v1\nkind: EncryptionConfig\n"));
+        assert_eq!(provider_config_flag(&config), "--experimental-encryption-provider-config");
+    }
+
+    #[test]
+    fn generated_keys_are_numbered_after_the_highest() {
+        let config: Config = toml::from_str("").unwrap();
+        let keys = [
+            key("key-2", EncryptionProvider::Aescbc),
+            key("key-7", EncryptionProvider::Aescbc),
+        ];
+        let generated = generate(&config, &keys).unwrap();
+        assert_eq!(generated.name, "key-8");
+        assert_eq!(base64::decode(&generated.secret).unwrap().len(), 32);
+        assert_eq!(generate(&config, &[]).unwrap().name, "key-1");
+    }
+}
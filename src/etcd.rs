@@ -3,19 +3,21 @@ use std::fs;
 
 use std;
 use std::fmt;
-use std::fs::File;
-use std::path::{Path, PathBuf};
-use std::io::prelude::*;
+use std::path::PathBuf;
 
-use config::Config;
+use config::{Config, ConfigError};
 use handlebars;
-use handlebars::Handlebars;
 
 use systemd;
 use systemd::Systemd;
-use resources::bootstrap::etcd::ETCD_BOOTSTRAP_CONFIG;
+use manifests;
+use inventory::{FileKind, Inventory, InventoryError};
+use resources::bootstrap::etcd::{ETCD_BOOTSTRAP_CONFIG, ETCD_DATA_DIR};
+use template;
+use workflow::failed;
 
 pub enum Phase {
+    Certificates,
     Bootstrap,
 }
 
@@ -52,6 +54,18 @@ impl From<systemd::SystemdError> for EtcdError {
     }
 }
 
+impl From<InventoryError> for EtcdError {
+    fn from(_error: InventoryError) -> EtcdError {
+        EtcdError::UnknownError
+    }
+}
+
+impl From<ConfigError> for EtcdError {
+    fn from(_error: ConfigError) -> EtcdError {
+        EtcdError::UnknownError
+    }
+}
+
 impl fmt::Debug for EtcdError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "EtcdError")
@@ -59,14 +73,27 @@ impl fmt::Debug for EtcdError {
 }
 
 impl<'a> Etcd<'a> {
-    pub fn bootstrap(config: &Config) {
-        Etcd::phase(Phase::Bootstrap, &config).run();
+    pub fn certificates(config: &Config) -> Result<(), EtcdError> {
+        Etcd::phase(Phase::Certificates, &config).run()
+    }
+
+    pub fn bootstrap(config: &Config) -> Result<(), EtcdError> {
+        Etcd::phase(Phase::Bootstrap, &config).run()
+    }
+
+    pub fn render(config: &Config) -> Result<(), EtcdError> {
+        Etcd::phase(Phase::Bootstrap, &config).write_configuration()?;
+        Ok(())
     }
 
     fn config_path(&self) -> PathBuf {
         PathBuf::from(&self.config.etcd.config_path)
     }
 
+    fn host_config_path(&self) -> PathBuf {
+        self.config.host_path(&self.config.etcd.config_path)
+    }
+
     fn phase(phase: Phase, config: &'a Config) -> Etcd<'a> {
         Etcd {
             phase: phase,
@@ -74,81 +101,121 @@ impl<'a> Etcd<'a> {
         }
     }
 
-    fn run(&self) {
+    fn run(&self) -> Result<(), EtcdError> {
         match self.phase {
-            Phase::Bootstrap => {
-                info!("bootstrapping");
-                if !Path::new(&self.config.etcd.config_path).is_dir() {
-                    fs::create_dir(&self.config.etcd.config_path).expect(
-                        "failed to create etcd config directory",
-                    );
+            Phase::Certificates => {
+                info!("generating certificates");
+                if !self.host_config_path().is_dir() {
+                    fs::create_dir(self.host_config_path())
+                        .map_err(EtcdError::from)
+                        .map_err(failed("failed to create etcd config directory"))?;
                 }
-                self.generate_certificates().expect(
+                self.generate_certificates().map_err(failed(
                     "failed certificate generation for etcd",
-                );
-                self.write_configuration().expect(
+                ))?;
+            }
+            Phase::Bootstrap => {
+                info!("bootstrapping");
+                let changed = self.write_configuration().map_err(failed(
                     "failed rendering configuration for etcd",
-                );
-                self.start_services().expect(
+                ))?;
+                self.start_services(changed).map_err(failed(
                     "failed starting services for etcd",
-                );
-                self.enable_services().expect(
+                ))?;
+                self.enable_services().map_err(failed(
                     "failed enabling services for etcd",
-                );
+                ))?;
             }
         }
+        Ok(())
+    }
+
+    fn pki_paths(&self) -> Vec<PathBuf> {
+        let secrets_path = PathBuf::from(&self.config.secrets.path);
+        vec![
+            secrets_path.join("ca.crt"),
+            secrets_path.join("ca.key"),
+            self.config_path().join("peer.crt"),
+            self.config_path().join("peer.key"),
+            self.config_path().join("server.crt"),
+            self.config_path().join("server.key"),
+        ]
     }
 
     fn generate_certificates(&self) -> Result<&Etcd, EtcdError> {
+        let missing = Inventory::missing(self.config, self.pki_paths());
         Certificate::new(
             "peer.crt",
-            &self.config.etcd.config_path,
+            self.host_config_path(),
             "etcd",
             "etcd-peer",
             vec![],
-            Key::new("peer.key", &self.config.etcd.config_path),
+            vec![],
+            Key::new("peer.key", self.host_config_path()),
             CaCertificate::new(self.config),
         ).present()?;
         Certificate::new(
             "server.crt",
-            &self.config.etcd.config_path,
+            self.host_config_path(),
             "etcd",
             "etcd-server",
             vec![],
-            Key::new("server.key", &self.config.etcd.config_path),
+            vec![],
+            Key::new("server.key", self.host_config_path()),
             CaCertificate::new(self.config),
         ).present()?;
+        Inventory::record_generated(self.config, missing, FileKind::Pki)?;
         Ok(self)
     }
 
-    fn write_configuration(&self) -> Result<&Etcd, EtcdError> {
+    fn write_configuration(&self) -> Result<bool, EtcdError> {
         match self.phase {
             Phase::Bootstrap => {
-                let mut reg = Handlebars::new();
-                let mut file = File::create(&self.config.etcd.config_file)?;
-                let config = reg.render_template(
-                    ETCD_BOOTSTRAP_CONFIG,
+                let environment = template::render(
+                    &manifests::template(
+                        self.config,
+                        manifests::BOOTSTRAP_ETCD_CONFIG,
+                        ETCD_BOOTSTRAP_CONFIG,
+                    )?,
                     &json!({
                     "etcd_ca_file_path": CaCertificate::new(&self.config).cert_path(),
                     "etcd_server_cert_file_path": self.config_path().join("server.crt"),
                     "etcd_server_key_file_path": self.config_path().join("server.key"),
                     "etcd_peer_cert_file_path": self.config_path().join("peer.crt"),
                     "etcd_peer_key_file_path": self.config_path().join("peer.key"),
+                    "etcd_data_dir": ETCD_DATA_DIR,
                 }),
                 )?;
-                file.write_all(&config.as_bytes())?;
+                Inventory::record_etcd_data_dir(self.config, ETCD_DATA_DIR)?;
+                Ok(Inventory::write_file(
+                    self.config,
+                    self.config.etcd_config_file()?,
+                    FileKind::Configuration,
+                    self.config
+                        .profile()?
+                        .environment_file(&environment)
+                        .as_bytes(),
+                )?)
             }
+            Phase::Certificates => Ok(false),
         }
-        Ok(self)
     }
 
-    fn start_services(&self) -> Result<&Etcd, EtcdError> {
-        Systemd::start("etcd.service")?;
+    fn start_services(&self, configuration_changed: bool) -> Result<&Etcd, EtcdError> {
+        if configuration_changed {
+            if self.config.profile()?.is_drop_in() {
+                Systemd::daemon_reload()?;
+            }
+            Systemd::restart("etcd.service")?;
+        } else {
+            Systemd::start("etcd.service")?;
+        }
         Ok(self)
     }
 
     fn enable_services(&self) -> Result<&Etcd, EtcdError> {
         Systemd::enable("etcd.service")?;
+        Inventory::record_service(self.config, "etcd.service")?;
         Ok(self)
     }
 }
@@ -0,0 +1,177 @@
+use config::{Cni, Config, DnsProvider};
+
+pub struct Component {
+    pub name: &'static str,
+    pub repository: &'static str,
+    pub prefixed: bool,
+    pub tag: Option<&'static str>,
+}
+
+pub const COMPONENTS: &'static [Component] = &[
+    Component {
+        name: "hyperkube",
+        repository: "hyperkube",
+        prefixed: true,
+        tag: None,
+    },
+    Component {
+        name: "pause",
+        repository: "pause",
+        prefixed: true,
+        tag: Some("3.1"),
+    },
+    Component {
+        name: "flannel",
+        repository: "quay.io/coreos/flannel",
+        prefixed: false,
+        tag: Some("v0.10.0-amd64"),
+    },
+    Component {
+        name: "flannel_cni",
+        repository: "quay.io/coreos/flannel-cni",
+        prefixed: false,
+        tag: Some("v0.3.0"),
+    },
+    Component {
+        name: "coredns",
+        repository: "coredns",
+        prefixed: true,
+        tag: Some("1.1.3"),
+    },
+    Component {
+        name: "kube_dns",
+        repository: "k8s-dns-kube-dns-amd64",
+        prefixed: true,
+        tag: Some("1.14.10"),
+    },
+    Component {
+        name: "dnsmasq_nanny",
+        repository: "k8s-dns-dnsmasq-nanny-amd64",
+        prefixed: true,
+        tag: Some("1.14.10"),
+    },
+    Component {
+        name: "dns_sidecar",
+        repository: "k8s-dns-sidecar-amd64",
+        prefixed: true,
+        tag: Some("1.14.10"),
+    },
+    Component {
+        name: "calico_node",
+        repository: "quay.io/calico/node",
+        prefixed: false,
+        tag: Some("v3.1.3"),
+    },
+    Component {
+        name: "calico_cni",
+        repository: "quay.io/calico/cni",
+        prefixed: false,
+        tag: Some("v3.1.3"),
+    },
+];
+
+pub fn component(name: &str) -> &'static Component {
+    COMPONENTS
+        .iter()
+        .find(|component| component.name == name)
+        .expect("unknown image component")
+}
+
+impl Component {
+    pub fn used(&self, config: &Config) -> bool {
+        match self.name {
+            "flannel" => config.net.cni == Cni::Flannel || config.net.cni == Cni::Canal,
+            "flannel_cni" => config.net.cni == Cni::Flannel,
+            "calico_node" | "calico_cni" => {
+                config.net.cni == Cni::Calico || config.net.cni == Cni::Canal
+            }
+            "coredns" => config.dns.provider == DnsProvider::CoreDns,
+            "kube_dns" | "dnsmasq_nanny" | "dns_sidecar" => {
+                config.dns.provider == DnsProvider::KubeDns
+            }
+            _ => true,
+        }
+    }
+
+    pub fn image(&self, config: &Config) -> String {
+        let image_override = config.images.component(self.name);
+        let repository = match image_override.and_then(|image| image.image.clone()) {
+            Some(repository) => repository,
+            None if self.prefixed => {
+                format!(
+                    "{}/{}",
+                    config.images.prefix.trim_right_matches('/'),
+                    self.repository
+                )
+            }
+            None => self.repository.to_string(),
+        };
+        if let Some(digest) = image_override.and_then(|image| image.digest.clone()) {
+            return format!("{}@{}", repository, digest);
+        }
+        let tag = match image_override.and_then(|image| image.tag.clone()) {
+            Some(tag) => tag,
+            None => {
+                self.tag.map(|tag| tag.to_string()).unwrap_or_else(|| {
+                    config.kubernetes.version.clone()
+                })
+            }
+        };
+        format!("{}:{}", repository, tag)
+    }
+}
+
+pub fn rewrite(config: &Config, image: &str) -> String {
+    let registry = match config.images.registry {
+        Some(ref registry) => registry.trim_right_matches('/'),
+        None => return image.to_string(),
+    };
+    let mut components: Vec<&str> = image.splitn(2, '/').collect();
+    if components.len() > 1 &&
+        (components[0].contains('.') || components[0].contains(':') ||
+             components[0] == "localhost")
+    {
+        components.remove(0);
+    }
+    format!("{}/{}", registry, components.join("/"))
+}
+
+pub fn upstream_images(config: &Config) -> Vec<(&'static str, String)> {
+    let mut images: Vec<(&'static str, String)> = COMPONENTS
+        .iter()
+        .filter(|component| component.used(config))
+        .map(|component| (component.name, component.image(config)))
+        .collect();
+    images.push(("kubeception", config.kubeception.image.clone()));
+    images
+}
+
+pub fn images(config: &Config) -> Vec<(&'static str, String)> {
+    upstream_images(config)
+        .into_iter()
+        .map(|(name, image)| (name, rewrite(config, &image)))
+        .collect()
+}
+
+pub fn mirror_mapping(config: &Config) -> String {
+    upstream_images(config)
+        .into_iter()
+        .map(|(_, image)| format!("{}={}\n", image, rewrite(config, &image)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use toml;
+
+    #[test]
+    fn pause_is_listed_and_rewritten() {
+        let config: Config = toml::from_str("[images]\nregistry = \"mirror.example.com:5000\"\n").unwrap();
+        assert!(images(&config).contains(&("pause", "mirror.example.com:5000/pause:3.1".to_string())));
+        assert!(mirror_mapping(&config).contains(
+            "k8s.gcr.io/pause:3.1=mirror.example.com:5000/pause:3.1\n",
+        ));
+    }
+}
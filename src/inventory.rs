@@ -0,0 +1,198 @@
+use std;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use openssl::sha;
+use serde_json;
+
+use config::Config;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileKind {
+    StaticManifest,
+    Configuration,
+    Kubeconfig,
+    Pki,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OwnedFile {
+    pub kind: FileKind,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Inventory {
+    #[serde(default)]
+    pub files: BTreeMap<String, OwnedFile>,
+    #[serde(default)]
+    pub services: Vec<String>,
+    #[serde(default)]
+    pub etcd_data_dir: Option<String>,
+}
+
+pub enum InventoryError {
+    UnknownError,
+}
+
+impl From<std::io::Error> for InventoryError {
+    fn from(_error: std::io::Error) -> InventoryError {
+        InventoryError::UnknownError
+    }
+}
+
+impl From<serde_json::Error> for InventoryError {
+    fn from(_error: serde_json::Error) -> InventoryError {
+        InventoryError::UnknownError
+    }
+}
+
+impl fmt::Debug for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InventoryError")
+    }
+}
+
+pub fn sha256(contents: &[u8]) -> String {
+    sha::sha256(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl Inventory {
+    pub fn path(config: &Config) -> PathBuf {
+        config.host_path(PathBuf::from(&config.secrets.path).join(
+            "kubeception-inventory.json",
+        ))
+    }
+
+    pub fn load(config: &Config) -> Result<Inventory, InventoryError> {
+        let path = Inventory::path(config);
+        if !path.exists() {
+            return Ok(Inventory::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, config: &Config) -> Result<(), InventoryError> {
+        fs::write(
+            Inventory::path(config),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn record_file<P: AsRef<Path>>(
+        config: &Config,
+        path: P,
+        kind: FileKind,
+        contents: &[u8],
+    ) -> Result<(), InventoryError> {
+        let mut inventory = Inventory::load(config)?;
+        inventory.files.insert(
+            path.as_ref().display().to_string(),
+            OwnedFile {
+                kind: kind,
+                sha256: sha256(contents),
+            },
+        );
+        inventory.save(config)
+    }
+
+    pub fn write_file<P: AsRef<Path>>(
+        config: &Config,
+        path: P,
+        kind: FileKind,
+        contents: &[u8],
+    ) -> Result<bool, InventoryError> {
+        let host_path = config.host_path(path.as_ref());
+        let changed = match fs::read(&host_path) {
+            Ok(current) => current != contents,
+            Err(_) => true,
+        };
+        if changed {
+            if let Some(parent) = host_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&host_path, contents)?;
+        }
+        Inventory::record_file(config, path, kind, contents)?;
+        Ok(changed)
+    }
+
+    pub fn remove_file<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), InventoryError> {
+        let host_path = config.host_path(path.as_ref());
+        if host_path.exists() {
+            fs::remove_file(&host_path)?;
+        }
+        let mut inventory = Inventory::load(config)?;
+        inventory.forget(&path.as_ref().display().to_string());
+        inventory.save(config)
+    }
+
+    pub fn record_generated(
+        config: &Config,
+        missing: Vec<PathBuf>,
+        kind: FileKind,
+    ) -> Result<(), InventoryError> {
+        for path in missing {
+            let host_path = config.host_path(&path);
+            if host_path.exists() {
+                Inventory::record_file(config, &path, kind, &fs::read(&host_path)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record_service<T: Into<String>>(config: &Config, service: T) -> Result<(), InventoryError> {
+        let service = service.into();
+        let mut inventory = Inventory::load(config)?;
+        if !inventory.services.contains(&service) {
+            inventory.services.push(service);
+        }
+        inventory.save(config)
+    }
+
+    pub fn record_etcd_data_dir<T: Into<String>>(
+        config: &Config,
+        etcd_data_dir: T,
+    ) -> Result<(), InventoryError> {
+        let mut inventory = Inventory::load(config)?;
+        inventory.etcd_data_dir = Some(etcd_data_dir.into());
+        inventory.save(config)
+    }
+
+    pub fn missing(config: &Config, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        paths
+            .into_iter()
+            .filter(|path| !config.host_path(path).exists())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.services.is_empty() && self.etcd_data_dir.is_none()
+    }
+
+    pub fn paths(&self, kind: FileKind) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|&(_, file)| file.kind == kind)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    pub fn unmodified(&self, path: &str, contents: &[u8]) -> bool {
+        match self.files.get(path) {
+            Some(file) => file.sha256 == sha256(contents),
+            None => false,
+        }
+    }
+
+    pub fn forget(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+}
@@ -3,30 +3,49 @@ use pki::*;
 use base64;
 
 use std;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::fmt;
-use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{thread, time};
-use std::io::Write;
 
 use serde_json;
+use serde_json::Value;
+use serde_yaml;
 
-use resources::bootstrap::k8s::{ALL_MANIFESTS as BOOTSTRAP_MANIFESTS, KUBECONFIG};
-use resources::bootstrap::kubelet::KUBELET_CONFIG as BOOTSTRAP_KUBELET_CONFIG;
+use resources::bootstrap::k8s::{ALL_MANIFESTS as BOOTSTRAP_MANIFESTS, IMAGE_PULL_POD,
+                                KUBECONFIG, KUBELET_PODS_URL};
+use resources::bootstrap::kubelet::{KUBELET_CONFIG as BOOTSTRAP_KUBELET_CONFIG,
+                                    KUBELET_DOCKER_CONFIG};
 
-use resources::control_plane::k8s::ALL_MANIFESTS as CONTROL_PLANE_MANIFESTS;
+use resources::control_plane::k8s::{ALL_MANIFESTS as CONTROL_PLANE_MANIFESTS,
+                                     IMAGE_PULL_SECRET, KUBE_APISERVER, KUBE_APISERVER_SECRET,
+                                     STALE_RBAC_RESOURCES};
+use resources::control_plane::audit::AUDIT_POLICY;
+use resources::control_plane::calico;
+use resources::control_plane::canal;
+use resources::control_plane::dns;
+use resources::control_plane::flannel;
 use resources::control_plane::kubelet::KUBELET_CONFIG as CONTROL_PLANE_KUBELET_CONFIG;
 
 use resources::control_plane::kubeception::ALL_MANIFESTS as KUBECEPTION_MANIFESTS;
+use resources::control_plane::oidc::OIDC_KUBECONFIG;
 
-use config::{Config, JoinConfig};
+use config::{Cni, Config, ConfigError, DnsProvider, FlannelBackend, JoinConfig, Patch, PatchType};
+use encryption;
+use encryption::{EncryptionError, EncryptionKey};
 use handlebars;
-use handlebars::Handlebars;
+use images;
+use manifests;
+use patch;
+use workflow::failed;
+use security;
 
 use openssl;
 use reqwest;
 
+use inventory::{sha256, FileKind, Inventory, InventoryError};
+
 use system::{System, SystemError};
 
 use systemd;
@@ -34,9 +53,14 @@ use systemd::Systemd;
 
 use kubectl::{Kubectl, KubectlError};
 
+use template;
+
 pub enum Phase {
+    Certificates,
+    PullImages,
     Bootstrap,
     DeployControlPlane,
+    LabelMaster,
     DeployKubelet,
 }
 
@@ -57,7 +81,6 @@ enum WhichCertificate {
 }
 
 enum WhichKey {
-    Admin,
     ServiceAccount,
 }
 
@@ -67,9 +90,72 @@ pub struct K8s<'a> {
 }
 
 pub enum K8sError {
+    ImagePullError { image: String, reason: String },
+    PatchError { target: String, reason: String },
+    FlagConflictError { component: String, flags: Vec<String> },
+    AddonError { failures: Vec<String> },
+    JoinError { reason: String },
+    ApiserverRestartError,
+    SingleApiserverError,
+    RolloutError { deployment: String },
+    CniChangeError { daemonset: String },
     UnknownError,
 }
 
+const CNI_DAEMONSETS: &'static [(Cni, &'static str)] = &[
+    (Cni::Flannel, "kube-flannel"),
+    (Cni::Calico, "calico-node"),
+    (Cni::Canal, "canal"),
+];
+
+// private keys and credentials in the manifest context that addons do not get
+const ADDON_EXCLUDED_KEYS: &'static [&'static str] = &[
+    "ca_key",
+    "client_crt",
+    "client_key",
+    "apiserver_key",
+    "etcd_client_crt",
+    "etcd_client_key",
+    "service_account_key",
+    "image_pull_secret",
+    "encryption_config",
+    "encryption_config_checksum",
+    "audit_webhook_config",
+];
+
+struct ContainerStatus {
+    name: String,
+    image: String,
+    pulled: bool,
+    reason: Option<String>,
+    message: Option<String>,
+}
+
+const IMAGE_PULL_FAILURES: &'static [&'static str] = &[
+    "ErrImagePull",
+    "ImagePullBackOff",
+    "InvalidImageName",
+    "ErrImageNeverPull",
+    "RegistryUnavailable",
+];
+
+impl ContainerStatus {
+    fn pull_failed(&self) -> bool {
+        match self.reason {
+            Some(ref reason) => IMAGE_PULL_FAILURES.contains(&reason.as_str()),
+            None => false,
+        }
+    }
+
+    fn failure(&self) -> String {
+        match (&self.reason, &self.message) {
+            (&Some(ref reason), &Some(ref message)) => format!("{}: {}", reason, message),
+            (&Some(ref reason), &None) => reason.clone(),
+            (&None, _) => "unknown".to_string(),
+        }
+    }
+}
+
 impl From<openssl::error::ErrorStack> for K8sError {
     fn from(_error: openssl::error::ErrorStack) -> Self {
         K8sError::UnknownError
@@ -112,49 +198,401 @@ impl From<systemd::SystemdError> for K8sError {
     }
 }
 
+impl From<InventoryError> for K8sError {
+    fn from(_error: InventoryError) -> K8sError {
+        K8sError::UnknownError
+    }
+}
+
+impl From<serde_json::Error> for K8sError {
+    fn from(_error: serde_json::Error) -> K8sError {
+        K8sError::UnknownError
+    }
+}
+
+impl From<EncryptionError> for K8sError {
+    fn from(_error: EncryptionError) -> K8sError {
+        K8sError::UnknownError
+    }
+}
+
 impl From<reqwest::Error> for K8sError {
     fn from(_error: reqwest::Error) -> K8sError {
         K8sError::UnknownError
     }
 }
 
+impl From<serde_yaml::Error> for K8sError {
+    fn from(_error: serde_yaml::Error) -> K8sError {
+        K8sError::UnknownError
+    }
+}
+
+impl From<ConfigError> for K8sError {
+    fn from(_error: ConfigError) -> K8sError {
+        K8sError::UnknownError
+    }
+}
+
 impl fmt::Debug for K8sError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "K8sError")
+        match *self {
+            K8sError::ImagePullError {
+                ref image,
+                ref reason,
+            } => write!(f, "could not pull image {}: {}", image, reason),
+            K8sError::PatchError {
+                ref target,
+                ref reason,
+            } => write!(f, "could not patch {}: {}", target, reason),
+            K8sError::FlagConflictError {
+                ref component,
+                ref flags,
+            } => write!(f, "{} sets {} more than once", component, flags.join(", ")),
+            K8sError::AddonError { ref failures } => {
+                write!(f, "could not apply addons {}", failures.join(", "))
+            }
+            K8sError::JoinError { ref reason } => write!(f, "could not join: {}", reason),
+            K8sError::ApiserverRestartError => {
+                write!(f, "kube-apiserver did not roll out the new encryption configuration")
+            }
+            K8sError::SingleApiserverError => {
+                write!(
+                    f,
+                    "rotating the encryption key restarts every kube-apiserver pod, which takes the only apiserver of this cluster down; join another master first"
+                )
+            }
+            K8sError::CniChangeError { ref daemonset } => {
+                write!(
+                    f,
+                    "the cluster runs the {} DaemonSet from another net.cni; changing the CNI of a bootstrapped cluster is not supported",
+                    daemonset
+                )
+            }
+            K8sError::RolloutError { ref deployment } => {
+                write!(f, "deployment {} has no available replicas", deployment)
+            }
+            K8sError::UnknownError => write!(f, "K8sError"),
+        }
+    }
+}
+
+fn apiserver_daemonset_status(status: &str) -> (u64, String) {
+    let mut fields = status.split_whitespace();
+    let apiservers = fields.next().and_then(|field| field.parse().ok()).unwrap_or(0);
+    let checksum = fields.next().unwrap_or_default().to_string();
+    (apiservers, checksum)
+}
+
+// replacing the pod of the only apiserver leaves nothing to recreate it, so a single apiserver
+// keeps its encryption configuration until it restarts
+fn rollout_checksum(checksum: &str, apiservers: u64, deployed: &str) -> String {
+    if apiservers == 1 && deployed != checksum {
+        return deployed.to_string();
+    }
+    checksum.to_string()
+}
+
+fn flag_name(arg: &str) -> Option<String> {
+    if !arg.starts_with("--") || arg.len() == 2 {
+        return None;
+    }
+    Some(arg.splitn(2, '=').next().unwrap_or(arg).to_string())
+}
+
+fn container_flags(document: &Value, container: &str) -> Vec<String> {
+    let no_values = Vec::new();
+    let pod_spec = if document["spec"]["template"].is_object() {
+        &document["spec"]["template"]["spec"]
+    } else {
+        &document["spec"]
+    };
+    let mut flags = Vec::new();
+    for pod_container in pod_spec["containers"].as_array().unwrap_or(&no_values) {
+        if pod_container["name"].as_str() != Some(container) {
+            continue;
+        }
+        for field in &["command", "args"] {
+            for arg in pod_container[*field].as_array().unwrap_or(&no_values) {
+                flags.extend(arg.as_str().and_then(flag_name));
+            }
+        }
+    }
+    flags
+}
+
+fn duplicate_flags(flags: &[String]) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut duplicates = Vec::new();
+    for flag in flags {
+        if !seen.insert(flag) && !duplicates.contains(flag) {
+            duplicates.push(flag.clone());
+        }
+    }
+    duplicates
+}
+
+fn check_flags(component: &str, flags: &[String]) -> Result<(), K8sError> {
+    let duplicates = duplicate_flags(flags);
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+    error!(
+        "{} sets {} more than once; check extra_args and feature_gates",
+        component,
+        duplicates.join(", ")
+    );
+    Err(K8sError::FlagConflictError {
+        component: component.to_string(),
+        flags: duplicates,
+    })
+}
+
+struct ManifestIdentity {
+    kind: String,
+    namespace: Option<String>,
+    name: String,
+}
+
+impl ManifestIdentity {
+    fn of(document: &Value) -> Option<ManifestIdentity> {
+        Some(ManifestIdentity {
+            kind: document["kind"].as_str()?.to_string(),
+            namespace: document["metadata"]["namespace"].as_str().map(|namespace| {
+                namespace.to_string()
+            }),
+            name: document["metadata"]["name"].as_str()?.to_string(),
+        })
+    }
+
+    fn targeted_by(&self, patch: &Patch) -> bool {
+        patch.kind == self.kind && patch.name == self.name &&
+            (patch.namespace.is_none() || patch.namespace == self.namespace)
+    }
+}
+
+impl fmt::Display for ManifestIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.namespace {
+            Some(ref namespace) => write!(f, "{} {}/{}", self.kind, namespace, self.name),
+            None => write!(f, "{} {}", self.kind, self.name),
+        }
+    }
+}
+
+fn manifest_documents(manifest: &str) -> Vec<String> {
+    let mut documents = vec![String::new()];
+    for line in manifest.lines() {
+        if line.trim_right() == "---" {
+            documents.push(String::new());
+            continue;
+        }
+        if let Some(document) = documents.last_mut() {
+            document.push_str(line);
+            document.push('\n');
+        }
     }
+    documents
+        .into_iter()
+        .filter(|document| {
+            document.lines().any(|line| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#')
+            })
+        })
+        .collect()
+}
+
+fn manifest_identity(document: &str) -> Option<ManifestIdentity> {
+    ManifestIdentity::of(&serde_yaml::from_str(document).ok()?)
 }
 
 impl<'a> K8s<'a> {
-    pub fn bootstrap(config: &Config) {
-        K8s::phase(Phase::Bootstrap, &config).run();
+    pub fn certificates(config: &Config) -> Result<(), K8sError> {
+        K8s::phase(Phase::Certificates, &config).run()
+    }
+
+    pub fn pull_images(config: &Config) -> Result<(), K8sError> {
+        K8s::phase(Phase::PullImages, &config).run()
+    }
+
+    pub fn bootstrap(config: &Config) -> Result<(), K8sError> {
+        K8s::phase(Phase::Bootstrap, &config).run()
     }
 
-    pub fn join(config: &Config, url: &String) {
+    pub fn join(config: &Config, url: &String) -> Result<(), K8sError> {
         let k8s = K8s::phase(Phase::DeployKubelet, &config);
-        let join_config = k8s.fetch_join_config(url).expect(
-            "could not fetch join information",
-        );
+        let join_config = k8s.fetch_join_config(url).map_err(
+            failed("could not fetch join information"),
+        )?;
         k8s.write_kubeconfig(KubeconfigType::Cluster, Some(join_config))
-            .expect("could not write kubeconfig information");
-        k8s.write_kubelet_config(KubeconfigType::Cluster).expect(
-            "could not write kubelet configuration",
-        );
+            .map_err(failed("could not write kubeconfig information"))?;
+        k8s.write_kubelet_config(KubeconfigType::Cluster).map_err(
+            failed("could not write kubelet configuration"),
+        )?;
+        k8s.create_audit_log_dir().map_err(
+            failed("could not create the audit log directory"),
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_node(config: &Config) -> Result<(), K8sError> {
+        let k8s = K8s::phase(Phase::DeployKubelet, &config);
+        Kubectl::new(&k8s).run(
+            &[
+                "delete",
+                "node",
+                "--ignore-not-found",
+                "--request-timeout=10s",
+                &System::hostname()?.to_owned(),
+            ],
+            None,
+            Some(&KubeconfigType::Cluster),
+        )?;
+        Ok(())
+    }
+
+    pub fn control_plane(config: &Config) -> Result<(), K8sError> {
+        K8s::phase(Phase::DeployControlPlane, &config).run()
+    }
+
+    pub fn render(config: &Config, output_dir: &Path) -> Result<(), K8sError> {
+        let k8s = K8s::phase(Phase::Bootstrap, &config);
+        k8s.write_configuration()?;
+        k8s.render_kubelet_configs(output_dir)?;
+        k8s.render_control_plane(output_dir)?;
+        k8s.render_addons(output_dir)?;
+        Ok(())
+    }
+
+    pub fn migrate_dns(config: &Config) -> Result<(), K8sError> {
+        let k8s = K8s::phase(Phase::DeployKubelet, &config);
+        k8s.wait_for_apiserver(Some(&ApiserverType::Cluster))?;
+        info!("deploying the cluster DNS");
+        k8s.check_patches()?;
+        for &(name, manifest) in k8s.dns_manifests() {
+            k8s.deploy_manifest(name, manifest)?;
+        }
+        k8s.remove_stale_dns()?;
+        Ok(())
+    }
+
+    pub fn rotate_encryption_key(config: &Config) -> Result<(), K8sError> {
+        if !config.encryption.enabled {
+            error!("encryption.enabled is false, there is no key to rotate");
+            return Err(K8sError::UnknownError);
+        }
+        let k8s = K8s::phase(Phase::DeployKubelet, &config);
+        k8s.wait_for_apiserver(Some(&ApiserverType::Cluster))?;
+        let (apiservers, _) = k8s.apiserver_daemonset()?;
+        if apiservers < 2 {
+            error!("{:?}", K8sError::SingleApiserverError);
+            return Err(K8sError::SingleApiserverError);
+        }
+        let keys = encryption::load(config)?;
+        let key = encryption::generate(config, &keys)?;
+        info!("adding encryption key {}", key.name);
+        let mut added = keys.clone();
+        added.push(key.clone());
+        k8s.deploy_encryption_keys(&added)?;
+        info!("encrypting new writes with {}", key.name);
+        let mut promoted = vec![key.clone()];
+        promoted.extend(keys.iter().cloned());
+        k8s.deploy_encryption_keys(&promoted)?;
+        info!("rewriting all secrets with {}", key.name);
+        k8s.rewrite_secrets()?;
+        info!("removing the previous encryption keys");
+        k8s.deploy_encryption_keys(&[key])?;
+        Ok(())
+    }
+
+    pub fn oidc_kubeconfig(
+        config: &Config,
+        user: &str,
+        server: Option<&str>,
+    ) -> Result<String, K8sError> {
+        let oidc = match config.auth.oidc {
+            Some(ref oidc) => oidc,
+            None => {
+                error!("auth.oidc is not configured");
+                return Err(K8sError::UnknownError);
+            }
+        };
+        let k8s = K8s::phase(Phase::DeployKubelet, &config);
+        let server = match server {
+            Some(server) => server.to_string(),
+            None => {
+                format!(
+                    "https://{}:{}",
+                    System::hostname()?,
+                    k8s.apiserver_port(Some(&KubeconfigType::Cluster))
+                )
+            }
+        };
+        let context = json!({
+            "server": server,
+            "ca_crt": base64::encode(&CaCertificate::new(config).cert()?.to_pem()?),
+            "oidc_user": user,
+            "oidc_client_id": oidc.client_id,
+            "oidc_issuer_url": oidc.issuer_url,
+            "oidc_ca": match k8s.oidc_ca()? {
+                Some(oidc_ca) => base64::encode(&oidc_ca),
+                None => String::new(),
+            },
+        });
+        Ok(template::render(
+            &manifests::template(config, manifests::OIDC_KUBECONFIG, OIDC_KUBECONFIG)?,
+            &context,
+        )?)
+    }
+
+    pub fn security_audit(config: &Config) -> Result<Vec<String>, K8sError> {
+        let k8s = K8s::phase(Phase::DeployKubelet, &config);
+        let kubectl = Kubectl::new(&k8s);
+        let roles = kubectl.output(
+            &[
+                "get",
+                "clusterroles,roles",
+                "--all-namespaces",
+                "--request-timeout=10s",
+                "--output=json",
+            ],
+            None,
+            None,
+        )?;
+        let bindings = kubectl.output(
+            &[
+                "get",
+                "clusterrolebindings,rolebindings",
+                "--all-namespaces",
+                "--request-timeout=10s",
+                "--output=json",
+            ],
+            None,
+            None,
+        )?;
+        Ok(security::over_privileged_bindings(
+            &serde_json::from_str(&roles)?,
+            &serde_json::from_str(&bindings)?,
+        ))
     }
 
-    pub fn control_plane(config: &Config) {
-        K8s::phase(Phase::DeployControlPlane, &config).run();
+    pub fn label_master(config: &Config) -> Result<(), K8sError> {
+        K8s::phase(Phase::LabelMaster, &config).run()
     }
 
-    pub fn kubelet(config: &Config) {
-        K8s::phase(Phase::DeployKubelet, &config).run();
+    pub fn pivot_kubelet(config: &Config) -> Result<(), K8sError> {
+        K8s::phase(Phase::DeployKubelet, &config).run()
     }
 
     pub fn fetch_join_config(&self, url: &String) -> Result<JoinConfig, K8sError> {
-        let join_uri = reqwest::Url::parse(url).expect("could not parse the provided URL");
+        let join_uri = reqwest::Url::parse(url).map_err(|_| {
+            K8sError::JoinError { reason: format!("{} is not a valid URL", url) }
+        })?;
         let response = reqwest::get(join_uri)?.text()?;
-        Ok(serde_json::from_str(&response).expect(
-            "failed to process the JSON response",
-        ))
+        serde_json::from_str(&response).map_err(|_| {
+            K8sError::JoinError { reason: format!("{} did not return join information", url) }
+        })
     }
 
     pub fn apiserver_port(&self, kubeconfig_type: Option<&KubeconfigType>) -> u16 {
@@ -163,8 +601,11 @@ impl<'a> K8s<'a> {
             Some(KubeconfigType::Cluster) => 6443,
             None => {
                 match self.phase {
+                    Phase::Certificates |
+                    Phase::PullImages |
                     Phase::Bootstrap |
-                    Phase::DeployControlPlane => {
+                    Phase::DeployControlPlane |
+                    Phase::LabelMaster => {
                         self.apiserver_port(Some(&KubeconfigType::Bootstrap))
                     }
                     Phase::DeployKubelet => self.apiserver_port(Some(&KubeconfigType::Cluster)),
@@ -183,8 +624,11 @@ impl<'a> K8s<'a> {
             }
             None => {
                 match self.phase {
+                    Phase::Certificates |
+                    Phase::PullImages |
                     Phase::Bootstrap |
-                    Phase::DeployControlPlane => {
+                    Phase::DeployControlPlane |
+                    Phase::LabelMaster => {
                         self.kubeconfig_path(Some(&KubeconfigType::Bootstrap))
                     }
                     Phase::DeployKubelet => self.kubeconfig_path(Some(&KubeconfigType::Cluster)),
@@ -193,6 +637,10 @@ impl<'a> K8s<'a> {
         }
     }
 
+    fn secrets_path(&self) -> PathBuf {
+        self.config.host_path(&self.config.secrets.path)
+    }
+
     fn bootstrap_manifests_path(&self) -> PathBuf {
         PathBuf::from(&self.config.bootstrap.manifests_path)
     }
@@ -204,36 +652,55 @@ impl<'a> K8s<'a> {
         }
     }
 
-    fn run(&self) {
+    fn run(&self) -> Result<(), K8sError> {
         match self.phase {
+            Phase::Certificates => {
+                info!("generating certificates");
+                self.generate_certificates().map_err(failed(
+                    "failed certificate generation for kubernetes",
+                ))?;
+            }
+            Phase::PullImages => {
+                info!("pulling images");
+                self.pull_images_with_kubelet().map_err(
+                    failed("failed to pull images"),
+                )?;
+            }
             Phase::Bootstrap => {
                 info!("bootstrapping");
-                self.generate_certificates().expect(
-                    "failed certificate generation for kubernetes",
-                );
-                self.write_configuration().expect(
+                self.write_configuration().map_err(failed(
                     "failed rendering configuration for kubernetes",
-                );
-                self.start_services().expect(
+                ))?;
+                self.create_audit_log_dir().map_err(failed(
+                    "failed creating the audit log directory",
+                ))?;
+                self.start_services().map_err(failed(
                     "failed starting services for kubernetes",
-                );
-                self.enable_services().expect(
+                ))?;
+                self.enable_services().map_err(failed(
                     "failed enabling services for kubernetes",
-                );
+                ))?;
             }
             Phase::DeployControlPlane => {
                 info!("deploying control plane");
-                self.deploy_control_plane().expect(
+                self.deploy_control_plane().map_err(failed(
                     "failed to deploy the control plane",
-                );
+                ))?;
+            }
+            Phase::LabelMaster => {
+                info!("registering local kubelet");
+                self.register_kubelet().map_err(failed(
+                    "failed to register the local kubelet",
+                ))?;
             }
             Phase::DeployKubelet => {
                 info!("configuring local kubelet");
-                self.deploy_kubelet().expect(
+                self.deploy_kubelet().map_err(failed(
                     "failed to configure the local kubelet",
-                );
+                ))?;
             }
         }
+        Ok(())
     }
 
     fn certificate(&self, certificate: WhichCertificate) -> Certificate {
@@ -241,33 +708,41 @@ impl<'a> K8s<'a> {
             WhichCertificate::Admin => {
                 Certificate::new(
                     "admin.crt",
-                    &self.config.secrets.path,
+                    self.secrets_path(),
                     "system:masters",
                     "admin",
                     vec![],
-                    Key::new("admin.key", &self.config.secrets.path),
+                    vec![],
+                    Key::new("admin.key", self.secrets_path()),
                     CaCertificate::new(self.config),
                 )
             }
             WhichCertificate::ApiServer => {
                 Certificate::new(
                     "apiserver.crt",
-                    &self.config.secrets.path,
+                    self.secrets_path(),
                     "kube-master",
                     "kube-apiserver",
                     vec![self.config.net.apiserver_cluster_ip.clone()],
-                    Key::new("apiserver.key", &self.config.secrets.path),
+                    vec![
+                        "kubernetes".to_string(),
+                        "kubernetes.default".to_string(),
+                        "kubernetes.default.svc".to_string(),
+                        format!("kubernetes.default.svc.{}", self.config.dns.domain),
+                    ],
+                    Key::new("apiserver.key", self.secrets_path()),
                     CaCertificate::new(self.config),
                 )
             }
             WhichCertificate::EtcdClient => {
                 Certificate::new(
                     "etcd-client.crt",
-                    &self.config.secrets.path,
+                    self.secrets_path(),
                     "etcd",
                     "etcd-client",
                     vec![],
-                    Key::new("etcd-client.key", &self.config.secrets.path),
+                    vec![],
+                    Key::new("etcd-client.key", self.secrets_path()),
                     CaCertificate::new(self.config),
                 )
             }
@@ -276,20 +751,54 @@ impl<'a> K8s<'a> {
 
     fn key(&self, key: WhichKey) -> Key {
         match key {
-            WhichKey::Admin => Key::new("admin.key", &self.config.secrets.path),
-            WhichKey::ServiceAccount => Key::new("service-account.key", &self.config.secrets.path),
+            WhichKey::ServiceAccount => Key::new("service-account.key", self.secrets_path()),
         }
     }
 
+    fn pki_paths(&self) -> Vec<PathBuf> {
+        let secrets_path = PathBuf::from(&self.config.secrets.path);
+        vec![
+            "ca.crt",
+            "ca.key",
+            "admin.crt",
+            "admin.key",
+            "apiserver.crt",
+            "apiserver.key",
+            "etcd-client.crt",
+            "etcd-client.key",
+            "service-account.key",
+        ].into_iter()
+            .map(|name| secrets_path.join(name))
+            .collect()
+    }
+
     fn generate_certificates(&self) -> Result<&K8s, K8sError> {
-        self.certificate(WhichCertificate::Admin).present()?;
-        self.certificate(WhichCertificate::ApiServer).present()?;
-        self.certificate(WhichCertificate::EtcdClient).present()?;
+        let mut generated = Inventory::missing(self.config, self.pki_paths());
+        for which in vec![
+            WhichCertificate::Admin,
+            WhichCertificate::ApiServer,
+            WhichCertificate::EtcdClient,
+        ]
+        {
+            let certificate = self.certificate(which);
+            if certificate.cert_path().exists() && !certificate.current()? {
+                generated.push(PathBuf::from(&self.config.secrets.path).join(
+                    certificate.name(),
+                ));
+            }
+            certificate.present()?;
+        }
         let service_account_key = self.key(WhichKey::ServiceAccount).present()?.public_key()?;
-        let mut file = File::create(PathBuf::from(&self.config.secrets.path).join(
-            "service-account.pub",
-        ))?;
-        file.write_all(&service_account_key.as_bytes())?;
+        Inventory::record_generated(self.config, generated, FileKind::Pki)?;
+        if self.config.encryption.enabled {
+            encryption::present(self.config)?;
+        }
+        Inventory::write_file(
+            self.config,
+            PathBuf::from(&self.config.secrets.path).join("service-account.pub"),
+            FileKind::Pki,
+            service_account_key.as_bytes(),
+        )?;
         Ok(self)
     }
 
@@ -298,74 +807,159 @@ impl<'a> K8s<'a> {
         kubeconfig_type: KubeconfigType,
         join_config: Option<JoinConfig>,
     ) -> Result<&K8s, K8sError> {
-        let mut file = File::create(self.kubeconfig_path(Some(&kubeconfig_type)))?;
+        let path = self.kubeconfig_path(Some(&kubeconfig_type));
         let config = if let Some(join_config) = join_config {
-            String::from_utf8(base64::decode(&join_config.kubeconfig).expect(
-                "could not decode join information",
-            )).expect("invalid UTF-8")
+            base64::decode(&join_config.kubeconfig)
+                .ok()
+                .and_then(|kubeconfig| String::from_utf8(kubeconfig).ok())
+                .ok_or_else(|| {
+                    K8sError::JoinError { reason: "the join kubeconfig is not valid".to_string() }
+                })?
         } else {
             self.kubeconfig_contents(kubeconfig_type)?
         };
-        file.write_all(&config.as_bytes())?;
+        Inventory::write_file(self.config, &path, FileKind::Kubeconfig, config.as_bytes())?;
         Ok(self)
     }
 
     fn write_kubelet_config(&self, kubeconfig_type: KubeconfigType) -> Result<&K8s, K8sError> {
-        let mut file = File::create(&self.config.kubelet.config_file)?;
         let config = self.kubelet_config_contents(kubeconfig_type)?;
-        file.write_all(&config.as_bytes())?;
+        if Inventory::write_file(
+            self.config,
+            self.config.kubelet_config_file()?,
+            FileKind::Configuration,
+            config.as_bytes(),
+        )?
+        {
+            self.restart_kubelet()?;
+        }
+        Ok(self)
+    }
+
+    fn restart_kubelet(&self) -> Result<&K8s, K8sError> {
+        if self.config.dry_run() {
+            return Ok(self);
+        }
+        if self.config.profile()?.is_drop_in() {
+            Systemd::daemon_reload()?;
+        }
         Systemd::restart("kubelet.service")?;
         Ok(self)
     }
 
     fn kubelet_config_contents(&self, kubeconfig_type: KubeconfigType) -> Result<String, K8sError> {
-        Ok(Handlebars::new().render_template(
-            CONTROL_PLANE_KUBELET_CONFIG,
-            &json!({
-                    "hostname": System::hostname()?,
-                    "kubeconfig_path": self.kubeconfig_path(Some(&kubeconfig_type)),
-                }),
-        )?)
+        let template =
+            manifests::template(self.config, manifests::KUBELET, CONTROL_PLANE_KUBELET_CONFIG)?;
+        let context = json!({
+            "hostname": System::hostname()?,
+            "kubeconfig_path": self.kubeconfig_path(Some(&kubeconfig_type)),
+            "dns_cluster_ip": &self.config.net.dns_cluster_ip,
+            "dns_domain": &self.config.dns.domain,
+            "pause_image": images::rewrite(
+                self.config,
+                &images::component("pause").image(self.config),
+            ),
+            "kubelet_extra_args": self.kubelet_extra_args(),
+        });
+        self.check_kubelet_flags("kubelet", &template, &context)?;
+        let environment = template::render(&template, &context)?;
+        Ok(self.config.profile()?.environment_file(&environment))
+    }
+
+    fn bootstrap_kubelet_config_contents(&self) -> Result<String, K8sError> {
+        let template = manifests::template(
+            self.config,
+            manifests::BOOTSTRAP_KUBELET,
+            BOOTSTRAP_KUBELET_CONFIG,
+        )?;
+        let context = self.template_context()?;
+        self.check_kubelet_flags("bootstrap kubelet", &template, &context)?;
+        let environment = template::render(&template, &context)?;
+        Ok(self.config.profile()?.environment_file(&environment))
     }
 
     fn kubeconfig_contents(&self, kubeconfig_type: KubeconfigType) -> Result<String, K8sError> {
-        Ok(
-            Handlebars::new().render_template(KUBECONFIG, &json!({
-                "apiserver_port": self.apiserver_port(Some(&kubeconfig_type)),
-                "ca_crt": base64::encode(&CaCertificate::new(self.config).cert()?.to_pem()?),
-                "client_crt": base64::encode(&self.certificate(WhichCertificate::Admin).present()?.cert()?),
-                "client_key": base64::encode(&self.key(WhichKey::Admin).key()?),
-            }))?
-        )
+        self.certificate(WhichCertificate::Admin).present()?;
+        let mut context = self.template_context()?;
+        context["apiserver_port"] = json!(self.apiserver_port(Some(&kubeconfig_type)));
+        Ok(template::render(
+            &manifests::template(self.config, manifests::BOOTSTRAP_KUBECONFIG, KUBECONFIG)?,
+            &context,
+        )?)
     }
 
     fn write_configuration(&self) -> Result<&K8s, K8sError> {
         match self.phase {
             Phase::Bootstrap => {
+                let context = self.template_context()?;
                 for (name, manifest) in BOOTSTRAP_MANIFESTS {
-                    let mut file = File::create(self.bootstrap_manifests_path().join(
-                        format!("{}.yaml", name),
-                    ))?;
-                    let config = Handlebars::new().render_template(
-                        manifest,
-                        &json!({
-                        "bootstrap_secrets_path": &self.config.secrets.path,
-                        "ca_certificates_path": &self.config.secrets.path,
-                    }),
+                    let path = self.bootstrap_manifests_path().join(format!("{}.yaml", name));
+                    let template =
+                        manifests::template(self.config, &manifests::bootstrap_name(name), manifest)?;
+                    self.check_container_flags(name, &template, &context)?;
+                    let config = template::render(&template, &context)?;
+                    Inventory::write_file(
+                        self.config,
+                        &path,
+                        FileKind::StaticManifest,
+                        config.as_bytes(),
                     )?;
-                    file.write_all(&config.as_bytes())?;
                 }
 
                 self.write_kubeconfig(KubeconfigType::Bootstrap, None)?;
                 self.write_kubeconfig(KubeconfigType::Cluster, None)?;
 
+                if let Some(encryption_config) = self.encryption_config()? {
+                    Inventory::write_file(
+                        self.config,
+                        encryption::config_path(self.config),
+                        FileKind::Pki,
+                        encryption_config.as_bytes(),
+                    )?;
+                }
+
+                if let Some(oidc_ca) = self.oidc_ca()? {
+                    Inventory::write_file(
+                        self.config,
+                        Path::new(&self.config.secrets.path).join("oidc-ca.crt"),
+                        FileKind::Configuration,
+                        &oidc_ca,
+                    )?;
+                }
+
+                if self.config.audit.enabled {
+                    Inventory::write_file(
+                        self.config,
+                        Path::new(&self.config.secrets.path).join("audit-policy.yaml"),
+                        FileKind::Configuration,
+                        &self.audit_policy()?,
+                    )?;
+                    if let Some(webhook_config) = self.audit_webhook_config()? {
+                        Inventory::write_file(
+                            self.config,
+                            Path::new(&self.config.secrets.path).join("audit-webhook.kubeconfig"),
+                            FileKind::Kubeconfig,
+                            &webhook_config,
+                        )?;
+                    }
+                }
+
+                self.write_image_pull_secret()?;
+
+                if Inventory::write_file(
+                    self.config,
+                    self.config.kubelet_config_file()?,
+                    FileKind::Configuration,
+                    self.bootstrap_kubelet_config_contents()?.as_bytes(),
+                )?
                 {
-                    let mut file = File::create(&self.config.kubelet.config_file)?;
-                    file.write_all(BOOTSTRAP_KUBELET_CONFIG.as_bytes())?;
-                    Systemd::restart("kubelet.service")?;
+                    self.restart_kubelet()?;
                 }
             }
+            Phase::Certificates |
+            Phase::PullImages |
             Phase::DeployControlPlane |
+            Phase::LabelMaster |
             Phase::DeployKubelet => {}
         }
         Ok(self)
@@ -378,6 +972,7 @@ impl<'a> K8s<'a> {
 
     fn enable_services(&self) -> Result<&K8s, K8sError> {
         Systemd::enable("kubelet.service")?;
+        Inventory::record_service(self.config, "kubelet.service")?;
         Ok(self)
     }
 
@@ -420,9 +1015,208 @@ impl<'a> K8s<'a> {
             }
             thread::sleep(time::Duration::new(1, 0));
         }
+        if let Ok(statuses) = self.kubelet_container_statuses(None) {
+            if let Some(status) = statuses.iter().find(|status| status.pull_failed()) {
+                let error = K8sError::ImagePullError {
+                    image: status.image.clone(),
+                    reason: status.failure(),
+                };
+                error!("apiserver did not start, {:?}", error);
+                return Err(error);
+            }
+        }
         Err(K8sError::UnknownError)
     }
 
+    fn kubelet_container_statuses(
+        &self,
+        pod_name: Option<&str>,
+    ) -> Result<Vec<ContainerStatus>, K8sError> {
+        let pods: Value = reqwest::get(KUBELET_PODS_URL)?.json()?;
+        let no_values = Vec::new();
+        let mut statuses = Vec::new();
+        for pod in pods["items"].as_array().unwrap_or(&no_values) {
+            if let Some(pod_name) = pod_name {
+                // static pods are suffixed with the node name
+                if !pod["metadata"]["name"].as_str().unwrap_or("").starts_with(
+                    pod_name,
+                )
+                {
+                    continue;
+                }
+            }
+            for status in pod["status"]["containerStatuses"].as_array().unwrap_or(
+                &no_values,
+            )
+            {
+                let waiting = &status["state"]["waiting"];
+                statuses.push(ContainerStatus {
+                    name: status["name"].as_str().unwrap_or("").to_string(),
+                    image: status["image"].as_str().unwrap_or("").to_string(),
+                    pulled: !status["imageID"].as_str().unwrap_or("").is_empty(),
+                    reason: waiting["reason"].as_str().map(|reason| reason.to_string()),
+                    message: waiting["message"].as_str().map(|message| message.to_string()),
+                });
+            }
+        }
+        Ok(statuses)
+    }
+
+    fn images_to_pull(&self) -> Vec<String> {
+        let mut images = images::images(self.config);
+        // the kubelet needs the pause image before it can start any other container
+        images.sort_by_key(|&(name, _)| name != "pause");
+        let mut images_to_pull = Vec::new();
+        for (_, image) in images {
+            if !images_to_pull.contains(&image) {
+                images_to_pull.push(image);
+            }
+        }
+        images_to_pull
+    }
+
+    fn image_pull_pod(&self, images_to_pull: &[String]) -> Value {
+        let containers: Vec<Value> = images_to_pull
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                // the command does not exist, so the container never runs; the kubelet
+                // still pulls its image
+                json!({
+                    "name": format!("image-{}", index),
+                    "image": image,
+                    "imagePullPolicy": "IfNotPresent",
+                    "command": ["/kubeception-image-pulled"],
+                })
+            })
+            .collect();
+        json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": IMAGE_PULL_POD,
+                "namespace": "kube-system",
+            },
+            "spec": {
+                "hostNetwork": true,
+                "restartPolicy": "Never",
+                "containers": containers,
+            },
+        })
+    }
+
+    fn pull_images_with_kubelet(&self) -> Result<&K8s, K8sError> {
+        self.write_image_pull_secret()?;
+        if Inventory::write_file(
+            self.config,
+            self.config.kubelet_config_file()?,
+            FileKind::Configuration,
+            self.bootstrap_kubelet_config_contents()?.as_bytes(),
+        )?
+        {
+            self.restart_kubelet()?;
+        }
+        self.start_services()?;
+        self.enable_services()?;
+        let images_to_pull = self.images_to_pull();
+        let path = self.bootstrap_manifests_path().join(
+            format!("{}.yaml", IMAGE_PULL_POD),
+        );
+        Inventory::write_file(
+            self.config,
+            &path,
+            FileKind::StaticManifest,
+            serde_json::to_string_pretty(&self.image_pull_pod(&images_to_pull))?
+                .as_bytes(),
+        )?;
+        let result = self.wait_for_images(&images_to_pull);
+        Inventory::remove_file(self.config, &path)?;
+        result?;
+        Ok(self)
+    }
+
+    fn wait_for_images(&self, images_to_pull: &[String]) -> Result<&K8s, K8sError> {
+        let mut pulled: Vec<String> = Vec::new();
+        let mut failures: BTreeMap<String, u32> = BTreeMap::new();
+        for _ in 1..1800 {
+            let statuses = match self.kubelet_container_statuses(Some(IMAGE_PULL_POD)) {
+                Ok(statuses) => statuses,
+                Err(_) => {
+                    thread::sleep(time::Duration::new(1, 0));
+                    continue;
+                }
+            };
+            for status in statuses {
+                let image = match status.name.trim_left_matches("image-").parse::<usize>() {
+                    Ok(index) if index < images_to_pull.len() => images_to_pull[index].clone(),
+                    _ => continue,
+                };
+                if status.pulled {
+                    failures.remove(&image);
+                    if !pulled.contains(&image) {
+                        pulled.push(image.clone());
+                        info!(
+                            "pulled {} ({}/{})",
+                            image,
+                            pulled.len(),
+                            images_to_pull.len()
+                        );
+                    }
+                } else if status.pull_failed() {
+                    let attempts = {
+                        let attempts = failures.entry(image.clone()).or_insert(0);
+                        *attempts += 1;
+                        *attempts
+                    };
+                    if attempts == 1 {
+                        warn!("could not pull {}: {}; retrying", image, status.failure());
+                    }
+                    if attempts >= 120 ||
+                        status.reason.as_ref().map(|reason| reason.as_str()) ==
+                            Some("InvalidImageName")
+                    {
+                        let error = K8sError::ImagePullError {
+                            image: image,
+                            reason: status.failure(),
+                        };
+                        error!("{:?}", error);
+                        return Err(error);
+                    }
+                }
+            }
+            if pulled.len() == images_to_pull.len() {
+                return Ok(self);
+            }
+            thread::sleep(time::Duration::new(1, 0));
+        }
+        // without the pause image the pod sandbox never starts and no container reports a status
+        let error = K8sError::ImagePullError {
+            image: images_to_pull
+                .iter()
+                .find(|image| !pulled.contains(image))
+                .cloned()
+                .unwrap_or_default(),
+            reason: "timed out waiting for the image to be pulled".to_string(),
+        };
+        error!("{:?}", error);
+        Err(error)
+    }
+
+    fn write_image_pull_secret(&self) -> Result<bool, K8sError> {
+        match self.image_pull_secret()? {
+            Some(image_pull_secret) => {
+                Inventory::write_file(
+                    self.config,
+                    KUBELET_DOCKER_CONFIG,
+                    FileKind::Configuration,
+                    &image_pull_secret,
+                )?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     pub fn wait_for_apiserver_stable(
         &self,
         apiserver_type: Option<&ApiserverType>,
@@ -495,16 +1289,27 @@ impl<'a> K8s<'a> {
 
     pub fn remove_static_manifests(&self) -> Result<&K8s, K8sError> {
         info!("removing static manifests");
-        let manifests = fs::read_dir(self.bootstrap_manifests_path())?;
-        for manifest in manifests {
-            fs::remove_file(manifest?.path())?;
+        let mut inventory = Inventory::load(self.config)?;
+        for manifest in inventory.paths(FileKind::StaticManifest) {
+            if Path::new(&manifest).exists() {
+                if !inventory.unmodified(&manifest, &fs::read(&manifest)?) {
+                    warn!(
+                        "static manifest {} was modified after kubeception wrote it",
+                        manifest
+                    );
+                }
+                fs::remove_file(&manifest)?;
+            }
+            inventory.forget(&manifest);
         }
+        inventory.save(self.config)?;
         Ok(self)
     }
 
-    fn deploy_manifest(&self, manifest: &str) -> Result<&K8s, K8sError> {
-        let kubectl = Kubectl::new(&self);
-        let processed_manifest = Handlebars::new().render_template(manifest, &json!({
+    fn template_context(&self) -> Result<Value, K8sError> {
+        let mut context = json!({
+            "bootstrap_secrets_path": &self.config.secrets.path,
+            "ca_certificates_path": &self.config.secrets.path,
             "apiserver_host": &System::hostname()?,
             "apiserver_port": self.apiserver_port(Some(&KubeconfigType::Cluster)),
             "ca_crt": base64::encode(&CaCertificate::new(self.config).cert()?.to_pem()?),
@@ -521,13 +1326,658 @@ impl<'a> K8s<'a> {
             "cluster_cidr": &self.config.net.cluster_cidr,
             "service_cluster_ip_range": &self.config.net.service_cluster_ip_range,
             "dns_cluster_ip": &self.config.net.dns_cluster_ip,
-            "kubeception_image": &self.config.kubeception.image,
+            "dns_domain": &self.config.dns.domain,
             "kubeception_nodeport": &self.config.kubeception.nodeport,
-        }))?;
+            "kubernetes_version": &self.config.kubernetes.version,
+            "image_pull_secret": match self.image_pull_secret()? {
+                Some(image_pull_secret) => base64::encode(&image_pull_secret),
+                None => String::new(),
+            },
+        });
+        for component in images::COMPONENTS {
+            context[format!("{}_image", component.name)] =
+                json!(images::rewrite(self.config, &component.image(self.config)));
+        }
+        context["kubeception_image"] = json!(images::rewrite(
+            self.config,
+            &self.config.kubeception.image,
+        ));
+        context["apiserver_args"] = json!(self.manifest_args(&self.config.apiserver.extra_args));
+        context["controller_manager_args"] =
+            json!(self.manifest_args(&self.config.controller_manager.extra_args));
+        context["scheduler_args"] = json!(self.manifest_args(&self.config.scheduler.extra_args));
+        context["proxy_args"] = json!(self.manifest_args(&self.config.proxy.extra_args));
+        context["kubelet_extra_args"] = json!(self.kubelet_extra_args());
+        let encryption_config = self.encryption_config()?;
+        context["encryption_enabled"] = json!(encryption_config.is_some());
+        context["encryption_config"] = json!(
+            encryption_config
+                .as_ref()
+                .map(|encryption_config| base64::encode(encryption_config))
+                .unwrap_or_default()
+        );
+        context["encryption_config_checksum"] = json!(
+            encryption_config
+                .as_ref()
+                .map(|encryption_config| sha256(encryption_config.as_bytes()))
+                .unwrap_or_default()
+        );
+        context["encryption_provider_config_flag"] =
+            json!(encryption::provider_config_flag(self.config));
+        match self.config.auth.oidc {
+            Some(ref oidc) => {
+                context["oidc_issuer_url"] = json!(oidc.issuer_url);
+                context["oidc_client_id"] = json!(oidc.client_id);
+                context["oidc_username_claim"] = json!(oidc.username_claim);
+                context["oidc_groups_claim"] = json!(oidc.groups_claim.clone().unwrap_or_default());
+            }
+            None => {
+                context["oidc_issuer_url"] = json!("");
+                context["oidc_client_id"] = json!("");
+                context["oidc_username_claim"] = json!("");
+                context["oidc_groups_claim"] = json!("");
+            }
+        }
+        context["oidc_ca"] = match self.oidc_ca()? {
+            Some(oidc_ca) => json!(base64::encode(&oidc_ca)),
+            None => json!(""),
+        };
+        let audit = &self.config.audit;
+        context["audit_enabled"] = json!(audit.enabled);
+        context["audit_policy"] = if audit.enabled {
+            json!(base64::encode(&self.audit_policy()?))
+        } else {
+            json!("")
+        };
+        context["audit_webhook_config"] = match self.audit_webhook_config()? {
+            Some(webhook_config) => json!(base64::encode(&webhook_config)),
+            None => json!(""),
+        };
+        context["audit_webhook_mode"] = json!(audit.webhook_mode.apiserver_value());
+        context["audit_log_dir"] = json!(audit.log_dir);
+        context["audit_max_age"] = json!(audit.max_age);
+        context["audit_max_backup"] = json!(audit.max_backup);
+        context["audit_max_size"] = json!(audit.max_size);
+        let flannel = &self.config.net.flannel;
+        context["flannel_backend"] = json!(flannel.backend.flannel_value());
+        context["flannel_vxlan_port"] = match flannel.backend {
+            FlannelBackend::Vxlan => json!(flannel.vxlan_port),
+            FlannelBackend::HostGw |
+            FlannelBackend::Wireguard => json!(false),
+        };
+        context["flannel_iface"] = match (&flannel.iface, &flannel.iface_regex) {
+            (&Some(ref iface), _) => json!(iface),
+            (&None, &Some(_)) => json!(false),
+            (&None, &None) => json!("$(POD_IP)"),
+        };
+        context["flannel_iface_regex"] = json!(flannel.iface_regex.clone().unwrap_or_default());
+        context["flannel_mtu"] = json!(flannel.mtu.unwrap_or(0));
+        let dns = &self.config.dns;
+        context["coredns_upstream_servers"] = if dns.upstream_servers.is_empty() {
+            json!("/etc/resolv.conf")
+        } else {
+            json!(dns.upstream_servers.join(" "))
+        };
+        context["coredns_stub_domains"] = json!(
+            dns.stub_domains
+                .iter()
+                .map(|(domain, servers)| {
+                    format!(
+                        "    {}:53 {{\n        errors\n        cache 30\n        forward . {}\n    }}\n",
+                        domain,
+                        servers.join(" ")
+                    )
+                })
+                .collect::<String>()
+        );
+        context["kube_dns_upstream_servers"] = if dns.upstream_servers.is_empty() {
+            json!("")
+        } else {
+            json!(serde_json::to_string(&dns.upstream_servers)?)
+        };
+        context["kube_dns_stub_domains"] = if dns.stub_domains.is_empty() {
+            json!("")
+        } else {
+            json!(serde_json::to_string(&dns.stub_domains)?)
+        };
+        context["calico_mtu"] = json!(self.config.net.calico.mtu);
+        context["calico_ipip_mode"] = json!(self.config.net.calico.ipip_mode.calico_value());
+        context["calico_ip_autodetection_method"] =
+            json!(self.config.net.calico.ip_autodetection_method);
+        Ok(context)
+    }
+
+    fn encryption_config(&self) -> Result<Option<String>, K8sError> {
+        if !self.config.encryption.enabled || !encryption::exists(self.config) {
+            return Ok(None);
+        }
+        Ok(Some(encryption::configuration(
+            self.config,
+            &encryption::load(self.config)?,
+        )))
+    }
+
+    fn deploy_encryption_keys(&self, keys: &[EncryptionKey]) -> Result<&K8s, K8sError> {
+        encryption::save(self.config, keys)?;
+        Inventory::write_file(
+            self.config,
+            encryption::config_path(self.config),
+            FileKind::Pki,
+            encryption::configuration(self.config, keys).as_bytes(),
+        )?;
+        self.deploy_manifest("kube-apiserver-secret", KUBE_APISERVER_SECRET)?;
+        self.deploy_manifest("kube-apiserver", KUBE_APISERVER)?;
+        self.wait_for_apiserver_rollout()?;
+        self.wait_for_apiserver_stable(Some(&ApiserverType::Cluster))
+    }
+
+    fn apiserver_daemonset(&self) -> Result<(u64, String), K8sError> {
+        let status = Kubectl::new(&self).output(
+            &[
+                "get",
+                "daemonset",
+                "--ignore-not-found",
+                "--namespace=kube-system",
+                "kube-apiserver",
+                "--output=jsonpath={.status.desiredNumberScheduled} \
+                 {.spec.template.metadata.annotations.checksum/encryption-config}",
+            ],
+            None,
+            None,
+        )?;
+        Ok(apiserver_daemonset_status(&status))
+    }
+
+    fn apiserver_encryption_checksum(&self, context: &mut Value) -> Result<(), K8sError> {
+        let checksum = context["encryption_config_checksum"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let (apiservers, deployed) = self.apiserver_daemonset()?;
+        let rollout = rollout_checksum(&checksum, apiservers, &deployed);
+        if rollout != checksum {
+            warn!(
+                "kube-apiserver runs on a single master; it uses the new encryption configuration the next time it restarts"
+            );
+        }
+        context["encryption_config_checksum"] = json!(rollout);
+        Ok(())
+    }
+
+    fn wait_for_apiserver_rollout(&self) -> Result<&K8s, K8sError> {
+        info!("waiting for kube-apiserver to roll out the new encryption configuration");
+        let kubectl = Kubectl::new(&self);
+        for _ in 1..600 {
+            // the apiserver being restarted may not answer
+            let status = kubectl.output(
+                &[
+                    "get",
+                    "daemonset",
+                    "--namespace=kube-system",
+                    "kube-apiserver",
+                    "--output=jsonpath={.metadata.generation} {.status.observedGeneration} \
+                     {.status.desiredNumberScheduled} {.status.updatedNumberScheduled} \
+                     {.status.numberAvailable}",
+                ],
+                None,
+                None,
+            );
+            if let Ok(status) = status {
+                let status: Vec<u64> = status
+                    .split_whitespace()
+                    .filter_map(|value| value.parse().ok())
+                    .collect();
+                if let &[generation, observed, desired, updated, available] = status.as_slice() {
+                    if observed >= generation && updated == desired && available == desired {
+                        return Ok(self);
+                    }
+                }
+            }
+            thread::sleep(time::Duration::new(1, 0));
+        }
+        error!("{:?}", K8sError::ApiserverRestartError);
+        Err(K8sError::ApiserverRestartError)
+    }
+
+    fn rewrite_secrets(&self) -> Result<&K8s, K8sError> {
+        let kubectl = Kubectl::new(&self);
+        let secrets = kubectl.output(
+            &["get", "secrets", "--all-namespaces", "--output=json"],
+            None,
+            None,
+        )?;
+        kubectl.run(&["replace", "--filename=-"], Some(&secrets), None)?;
+        Ok(self)
+    }
+
+    fn oidc_ca(&self) -> Result<Option<Vec<u8>>, K8sError> {
+        match self.config.auth.oidc {
+            Some(ref oidc) => {
+                match oidc.ca_file {
+                    Some(ref ca_file) => Ok(Some(fs::read(ca_file)?)),
+                    None => Ok(None),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn create_audit_log_dir(&self) -> Result<(), K8sError> {
+        if !self.config.audit.enabled || self.config.dry_run() {
+            return Ok(());
+        }
+        let path = self.config.host_path(&self.config.audit.log_dir);
+        fs::create_dir_all(&path)?;
+        System::chown(&path, 65534, 65534)?;
+        Ok(())
+    }
+
+    fn audit_policy(&self) -> Result<Vec<u8>, K8sError> {
+        match self.config.audit.policy_file {
+            Some(ref policy_file) => Ok(fs::read(policy_file)?),
+            None => {
+                let policy = manifests::template(
+                    self.config,
+                    manifests::AUDIT_POLICY,
+                    AUDIT_POLICY,
+                )?;
+                Ok(policy.trim_left_matches('\n').as_bytes().to_vec())
+            }
+        }
+    }
+
+    fn audit_webhook_config(&self) -> Result<Option<Vec<u8>>, K8sError> {
+        match self.config.audit.webhook_config_file {
+            Some(ref webhook_config_file) if self.config.audit.enabled => {
+                Ok(Some(fs::read(webhook_config_file)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn image_pull_secret(&self) -> Result<Option<Vec<u8>>, K8sError> {
+        match self.config.images.pull_secret {
+            Some(ref pull_secret) => Ok(Some(fs::read(pull_secret)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn control_plane_manifests(&self) -> Vec<(&'static str, &'static str)> {
+        let mut manifests = Vec::new();
+        if self.config.images.pull_secret.is_some() {
+            manifests.push(("image-pull-secret", IMAGE_PULL_SECRET));
+        }
+        match self.config.net.cni {
+            Cni::Flannel => manifests.extend(flannel::ALL_MANIFESTS),
+            Cni::Calico => {
+                manifests.extend(calico::CUSTOM_RESOURCE_DEFINITIONS);
+                manifests.extend(calico::ALL_MANIFESTS);
+            }
+            Cni::Canal => {
+                manifests.extend(calico::CUSTOM_RESOURCE_DEFINITIONS);
+                manifests.extend(canal::ALL_MANIFESTS);
+            }
+            Cni::None => {}
+        }
+        manifests.extend(CONTROL_PLANE_MANIFESTS);
+        manifests.extend(self.dns_manifests());
+        manifests.extend(KUBECEPTION_MANIFESTS);
+        manifests
+    }
+
+    fn dns_manifests(&self) -> &'static [(&'static str, &'static str)] {
+        match self.config.dns.provider {
+            DnsProvider::CoreDns => dns::COREDNS_MANIFESTS,
+            DnsProvider::KubeDns => dns::KUBE_DNS_MANIFESTS,
+        }
+    }
+
+    fn check_cni(&self) -> Result<&K8s, K8sError> {
+        let kubectl = Kubectl::new(&self);
+        for &(cni, daemonset) in CNI_DAEMONSETS {
+            if cni == self.config.net.cni {
+                continue;
+            }
+            let deployed = kubectl.output(
+                &[
+                    "get",
+                    "--namespace=kube-system",
+                    "--ignore-not-found",
+                    "--output=name",
+                    "daemonset",
+                    daemonset,
+                ],
+                None,
+                None,
+            )?;
+            if !deployed.trim().is_empty() {
+                let error = K8sError::CniChangeError { daemonset: daemonset.to_string() };
+                error!("{:?}", error);
+                return Err(error);
+            }
+        }
+        Ok(self)
+    }
+
+    fn remove_stale_dns(&self) -> Result<&K8s, K8sError> {
+        let (deployment, stale_deployment, stale_resources) = match self.config.dns.provider {
+            DnsProvider::CoreDns => ("coredns", "kube-dns", dns::KUBE_DNS_RESOURCES),
+            DnsProvider::KubeDns => ("kube-dns", "coredns", dns::COREDNS_RESOURCES),
+        };
+        let stale = Kubectl::new(&self).output(
+            &[
+                "get",
+                "--namespace=kube-system",
+                "--ignore-not-found",
+                "--output=name",
+                "deployment",
+                stale_deployment,
+            ],
+            None,
+            None,
+        )?;
+        if !stale.trim().is_empty() {
+            self.wait_for_deployment(deployment)?;
+        }
+        self.remove_resources(stale_resources)
+    }
+
+    fn wait_for_deployment(&self, deployment: &str) -> Result<&K8s, K8sError> {
+        info!("waiting for deployment {} to become available", deployment);
+        let kubectl = Kubectl::new(&self);
+        for _ in 1..300 {
+            let available = kubectl.output(
+                &[
+                    "get",
+                    "--namespace=kube-system",
+                    "--output=jsonpath={.status.availableReplicas}",
+                    "deployment",
+                    deployment,
+                ],
+                None,
+                None,
+            );
+            if let Ok(available) = available {
+                if available.trim().parse::<u32>().unwrap_or(0) >= 1 {
+                    return Ok(self);
+                }
+            }
+            thread::sleep(time::Duration::new(1, 0));
+        }
+        let error = K8sError::RolloutError { deployment: deployment.to_string() };
+        error!("{:?}", error);
+        Err(error)
+    }
+
+    fn remove_resources(&self, resources: &[(&str, &str)]) -> Result<&K8s, K8sError> {
+        let kubectl = Kubectl::new(&self);
+        for &(kind, name) in resources {
+            kubectl.run(
+                &[
+                    "delete",
+                    "--namespace=kube-system",
+                    "--ignore-not-found",
+                    kind,
+                    name,
+                ],
+                None,
+                None,
+            )?;
+        }
+        Ok(self)
+    }
+
+    fn render_manifest(&self, name: &str, manifest: &'static str) -> Result<String, K8sError> {
+        let manifest = manifests::template(
+            self.config,
+            &manifests::control_plane_name(name),
+            manifest,
+        )?;
+        let mut context = self.template_context()?;
+        if name == "kube-apiserver" && !self.config.dry_run() {
+            self.apiserver_encryption_checksum(&mut context)?;
+        }
+        self.check_container_flags(name, &manifest, &context)?;
+        Ok(template::render(&manifest, &context)?)
+    }
+
+    fn flag_checked_container(
+        &self,
+        name: &str,
+    ) -> Option<(&'static str, &'static str, &BTreeMap<String, String>)> {
+        match name {
+            "apiserver" | "kube-apiserver" => Some((
+                "kube-apiserver",
+                "apiserver_args",
+                &self.config.apiserver.extra_args,
+            )),
+            "controller_manager" |
+            "kube-controller-manager" => Some((
+                "kube-controller-manager",
+                "controller_manager_args",
+                &self.config.controller_manager.extra_args,
+            )),
+            "scheduler" | "kube-scheduler" => Some((
+                "kube-scheduler",
+                "scheduler_args",
+                &self.config.scheduler.extra_args,
+            )),
+            "kube-proxy" => Some(("kube-proxy", "proxy_args", &self.config.proxy.extra_args)),
+            _ => None,
+        }
+    }
+
+    fn check_container_flags(
+        &self,
+        name: &str,
+        template: &str,
+        context: &Value,
+    ) -> Result<&K8s, K8sError> {
+        let (container, args_key, extra_args) = match self.flag_checked_container(name) {
+            Some(container) => container,
+            None => return Ok(self),
+        };
+        let mut builtin_context = context.clone();
+        builtin_context[args_key] = json!([]);
+        let mut flags = Vec::new();
+        for document in manifest_documents(&template::render(template, &builtin_context)?) {
+            if let Ok(document) = serde_yaml::from_str::<Value>(&document) {
+                flags.extend(container_flags(&document, container));
+            }
+        }
+        flags.extend(self.component_args(extra_args).iter().filter_map(
+            |arg| flag_name(arg),
+        ));
+        check_flags(container, &flags)?;
+        Ok(self)
+    }
+
+    fn check_kubelet_flags(
+        &self,
+        component: &str,
+        template: &str,
+        context: &Value,
+    ) -> Result<&K8s, K8sError> {
+        let mut builtin_context = context.clone();
+        builtin_context["kubelet_extra_args"] = json!("");
+        let environment = template::render(template, &builtin_context)?;
+        let mut flags: Vec<String> = environment
+            .split(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+            .filter_map(flag_name)
+            .collect();
+        flags.extend(
+            self.component_args(&self.config.kubelet.extra_args)
+                .iter()
+                .filter_map(|arg| flag_name(arg)),
+        );
+        check_flags(component, &flags)?;
+        Ok(self)
+    }
+
+    fn component_args(&self, extra_args: &BTreeMap<String, String>) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.config.feature_gates.is_empty() {
+            let feature_gates: Vec<String> = self.config
+                .feature_gates
+                .iter()
+                .map(|(name, enabled)| format!("{}={}", name, enabled))
+                .collect();
+            args.push(format!("--feature-gates={}", feature_gates.join(",")));
+        }
+        for (name, value) in extra_args {
+            args.push(format!("--{}={}", name, value));
+        }
+        args
+    }
+
+    fn manifest_args(&self, extra_args: &BTreeMap<String, String>) -> Vec<String> {
+        self.component_args(extra_args)
+            .iter()
+            .map(|arg| format!("'{}'", arg.replace('\'', "''")))
+            .collect()
+    }
+
+    fn kubelet_extra_args(&self) -> String {
+        self.component_args(&self.config.kubelet.extra_args)
+            .iter()
+            .map(|arg| format!(" {}", arg))
+            .collect()
+    }
+
+    fn patch_manifest(&self, manifest: String) -> Result<String, K8sError> {
+        if self.config.patches.is_empty() {
+            return Ok(manifest);
+        }
+        let mut documents = Vec::new();
+        let mut patched = false;
+        for document in manifest_documents(&manifest) {
+            match self.patch_document(&document)? {
+                Some(patched_document) => {
+                    documents.push(patched_document);
+                    patched = true;
+                }
+                None => documents.push(document),
+            }
+        }
+        if !patched {
+            return Ok(manifest);
+        }
+        Ok(documents.join("---\n"))
+    }
+
+    fn patch_document(&self, document: &str) -> Result<Option<String>, K8sError> {
+        let mut value: Value = match serde_yaml::from_str(document) {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        let identity = match ManifestIdentity::of(&value) {
+            Some(identity) => identity,
+            None => return Ok(None),
+        };
+        let mut patched = false;
+        for (index, patch) in self.config.patches.iter().enumerate() {
+            if !identity.targeted_by(patch) {
+                continue;
+            }
+            info!("patching {}", identity);
+            self.apply_patch(&mut value, patch).map_err(|reason| {
+                error!(
+                    "patches.{} could not be applied to {}: {}",
+                    index,
+                    identity,
+                    reason
+                );
+                K8sError::PatchError {
+                    target: identity.to_string(),
+                    reason: format!("patches.{}: {}", index, reason),
+                }
+            })?;
+            patched = true;
+        }
+        if !patched {
+            return Ok(None);
+        }
+        let patched_document = serde_yaml::to_string(&value)?;
+        Ok(Some(format!(
+            "{}\n",
+            patched_document.trim_left_matches("---").trim_left()
+        )))
+    }
+
+    fn apply_patch(&self, document: &mut Value, patch: &Patch) -> Result<(), String> {
+        match patch.patch_type {
+            PatchType::Json => {
+                let operations: Vec<Value> = serde_json::from_str(&patch.patch).map_err(
+                    |error| error.to_string(),
+                )?;
+                patch::apply(document, &operations)
+            }
+            PatchType::Merge => {
+                let merge_patch: Value = serde_yaml::from_str(&patch.patch).map_err(
+                    |error| error.to_string(),
+                )?;
+                patch::merge(document, &merge_patch);
+                Ok(())
+            }
+            PatchType::Strategic => {
+                let input = serde_yaml::to_string(document).map_err(
+                    |error| error.to_string(),
+                )?;
+                let output = Kubectl::new(&self)
+                    .local(
+                        &[
+                            "patch",
+                            "--local",
+                            "--filename=-",
+                            "--output=json",
+                            "--type=strategic",
+                            &format!("--patch={}", patch.patch),
+                        ],
+                        Some(&input),
+                    )
+                    .map_err(|_| "kubectl patch --local failed".to_string())?;
+                *document = serde_json::from_str(&output).map_err(
+                    |error| error.to_string(),
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    fn check_patches(&self) -> Result<&K8s, K8sError> {
+        if self.config.patches.is_empty() {
+            return Ok(self);
+        }
+        let mut identities = Vec::new();
+        for (name, manifest) in self.control_plane_manifests() {
+            for document in manifest_documents(&self.render_manifest(name, manifest)?) {
+                identities.extend(manifest_identity(&document));
+            }
+        }
+        for (index, patch) in self.config.patches.iter().enumerate() {
+            if !identities.iter().any(|identity| identity.targeted_by(patch)) {
+                error!(
+                    "patches.{} targets {} {}, which is not deployed",
+                    index,
+                    patch.kind,
+                    patch.name
+                );
+                return Err(K8sError::PatchError {
+                    target: format!("{}/{}", patch.kind, patch.name),
+                    reason: format!("patches.{} does not match any manifest", index),
+                });
+            }
+        }
+        Ok(self)
+    }
+
+    fn deploy_manifest(&self, name: &str, manifest: &'static str) -> Result<&K8s, K8sError> {
+        let kubectl = Kubectl::new(&self);
+        let processed_manifest = self.patch_manifest(self.render_manifest(name, manifest)?)?;
         kubectl.run(
             &["apply", "-f", "-"],
             Some(&processed_manifest),
-            Some(&KubeconfigType::Bootstrap),
+            None,
         )?;
         Ok(self)
     }
@@ -535,16 +1985,167 @@ impl<'a> K8s<'a> {
     pub fn deploy_control_plane(&self) -> Result<&K8s, K8sError> {
         info!("applying control plane manifests");
         self.wait_for_apiserver(Some(&ApiserverType::Bootstrap))?;
-        for manifest in CONTROL_PLANE_MANIFESTS {
-            self.deploy_manifest(manifest)?;
+        self.check_cni()?;
+        if self.config.encryption.enabled {
+            encryption::present(self.config)?;
         }
-        for manifest in KUBECEPTION_MANIFESTS {
-            self.deploy_manifest(manifest)?;
+        self.check_patches()?;
+        for (name, manifest) in self.control_plane_manifests() {
+            self.deploy_manifest(name, manifest)?;
         }
+        self.remove_stale_dns()?;
+        self.remove_resources(STALE_RBAC_RESOURCES)?;
+        self.deploy_addons()?;
         Ok(self)
     }
 
-    pub fn deploy_kubelet(&self) -> Result<&K8s, K8sError> {
+    fn addons(&self) -> Result<Vec<PathBuf>, K8sError> {
+        let addons_path = match self.config.addons.path {
+            Some(ref addons_path) => addons_path,
+            None => return Ok(Vec::new()),
+        };
+        let entries = fs::read_dir(addons_path).map_err(|error| {
+            error!("could not read addons from {}: {}", addons_path, error);
+            error
+        })?;
+        let mut addons = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let hidden = path.file_name()
+                .map(|name| name.to_string_lossy().starts_with('.'))
+                .unwrap_or(true);
+            let manifest = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("yaml") | Some("yml") | Some("hbs") => true,
+                _ => false,
+            };
+            if path.is_file() && manifest && !hidden {
+                addons.push(path);
+            }
+        }
+        addons.sort();
+        Ok(addons)
+    }
+
+    fn addon_name(addon: &Path) -> String {
+        addon
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn addon_context(&self) -> Result<Value, K8sError> {
+        Ok(K8s::addon_values(self.template_context()?))
+    }
+
+    fn addon_values(mut context: Value) -> Value {
+        if let Some(values) = context.as_object_mut() {
+            for key in ADDON_EXCLUDED_KEYS {
+                values.remove(*key);
+            }
+        }
+        context
+    }
+
+    fn render_addon(&self, addon: &Path, context: &Value) -> Result<String, String> {
+        let template = fs::read_to_string(addon).map_err(
+            |error| format!("could not read it: {}", error),
+        )?;
+        template::render(&template, context).map_err(|error| {
+            format!("could not render it: {}", error)
+        })
+    }
+
+    fn addons_result(&self, failures: Vec<String>) -> Result<&K8s, K8sError> {
+        if failures.is_empty() {
+            return Ok(self);
+        }
+        Err(K8sError::AddonError { failures: failures })
+    }
+
+    fn deploy_addons(&self) -> Result<&K8s, K8sError> {
+        let addons = self.addons()?;
+        if addons.is_empty() {
+            return Ok(self);
+        }
+        info!("applying addons");
+        let context = self.addon_context()?;
+        let kubectl = Kubectl::new(&self);
+        let mut failures = Vec::new();
+        for addon in addons {
+            let name = K8s::addon_name(&addon);
+            let result = self.render_addon(&addon, &context).and_then(|manifest| {
+                kubectl
+                    .run(&["apply", "-f", "-"], Some(&manifest), None)
+                    .map_err(|_| "kubectl apply failed".to_string())
+            });
+            match result {
+                Ok(()) => info!("applied addon {}", name),
+                Err(reason) => {
+                    error!("addon {}: {}", name, reason);
+                    failures.push(name);
+                }
+            }
+        }
+        self.addons_result(failures)
+    }
+
+    fn render_addons(&self, output_dir: &Path) -> Result<&K8s, K8sError> {
+        let addons = self.addons()?;
+        if addons.is_empty() {
+            return Ok(self);
+        }
+        let addons_path = output_dir.join("addons");
+        fs::create_dir_all(&addons_path)?;
+        let context = self.addon_context()?;
+        let mut failures = Vec::new();
+        for addon in addons {
+            let name = K8s::addon_name(&addon);
+            match self.render_addon(&addon, &context) {
+                Ok(manifest) => {
+                    fs::write(addons_path.join(name.trim_right_matches(".hbs")), manifest)?
+                }
+                Err(reason) => {
+                    error!("addon {}: {}", name, reason);
+                    failures.push(name);
+                }
+            }
+        }
+        self.addons_result(failures)
+    }
+
+    fn render_control_plane(&self, output_dir: &Path) -> Result<&K8s, K8sError> {
+        let control_plane_path = output_dir.join("control-plane");
+        fs::create_dir_all(&control_plane_path)?;
+        self.check_patches()?;
+        for (index, (name, manifest)) in self.control_plane_manifests().into_iter().enumerate() {
+            fs::write(
+                control_plane_path.join(format!("{:02}-{}.yaml", index, name)),
+                self.patch_manifest(self.render_manifest(name, manifest)?)?,
+            )?;
+        }
+        Ok(self)
+    }
+
+    fn render_kubelet_configs(&self, output_dir: &Path) -> Result<&K8s, K8sError> {
+        let kubelet_path = output_dir.join("kubelet");
+        fs::create_dir_all(&kubelet_path)?;
+        fs::write(
+            kubelet_path.join("bootstrap"),
+            self.bootstrap_kubelet_config_contents()?,
+        )?;
+        fs::write(
+            kubelet_path.join("bootstrap-apiserver"),
+            self.kubelet_config_contents(KubeconfigType::Bootstrap)?,
+        )?;
+        fs::write(
+            kubelet_path.join("cluster-apiserver"),
+            self.kubelet_config_contents(KubeconfigType::Cluster)?,
+        )?;
+        self.write_kubelet_config(KubeconfigType::Cluster)?;
+        Ok(self)
+    }
+
+    pub fn register_kubelet(&self) -> Result<&K8s, K8sError> {
         self.wait_for_apiserver(Some(&ApiserverType::Bootstrap))?;
 
         info!("pointing the kubelet to the boostrap apiserver");
@@ -552,6 +2153,11 @@ impl<'a> K8s<'a> {
 
         self.wait_for_kubelet_to_be_registered()?;
         self.label_node_as_master()?;
+
+        Ok(self)
+    }
+
+    pub fn deploy_kubelet(&self) -> Result<&K8s, K8sError> {
         self.wait_for_apiserver_stable(
             Some(&ApiserverType::Cluster),
         )?;
@@ -564,3 +2170,262 @@ impl<'a> K8s<'a> {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use migration;
+    use toml;
+
+    const MANIFESTS: &'static str = r#"
+# Leading comment
+---
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: coredns
+  namespace: kube-system
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  labels:
+    name: not-this-one
+  name: "coredns"
+  namespace: kube-system
+spec:
+  replicas: 2
+  template:
+    spec:
+      containers:
+      - name: coredns
+---
+"#;
+
+    fn config(patches: &str) -> Config {
+        toml::from_str(patches).unwrap()
+    }
+
+    #[test]
+    fn baseline_configs_keep_kube_dns() {
+        let (value, _) = Config::migrated_value(
+            "[secrets]\npath = \"/etc/kubernetes/bootstrap-secrets\"\n\n\
+             [net]\ncluster_cidr = \"10.2.0.0/16\"\nservice_cluster_ip_range = \"10.3.0.0/24\"\n\
+             dns_cluster_ip = \"10.3.0.10\"\n\n\
+             [kubeception]\nimage = \"ereslibre/kubeception:latest\"\nnodeport = \"30000\"\n",
+        ).unwrap();
+        let baseline: Config = value.try_into().unwrap();
+        let k8s = K8s::phase(Phase::DeployControlPlane, &baseline);
+        assert!(k8s.dns_manifests() == dns::KUBE_DNS_MANIFESTS);
+        let current = config(&format!("apiVersion = \"{}\"\n", migration::API_VERSION));
+        assert!(current.dns.provider == DnsProvider::CoreDns);
+    }
+
+    #[test]
+    fn images_are_pulled_through_a_static_pod() {
+        let config = config("[images]\nregistry = \"mirror.example.com:5000\"\n");
+        let k8s = K8s::phase(Phase::PullImages, &config);
+        let images_to_pull = k8s.images_to_pull();
+        assert_eq!(images_to_pull[0], "mirror.example.com:5000/pause:3.1");
+        let pod = k8s.image_pull_pod(&images_to_pull);
+        let containers = pod["spec"]["containers"].as_array().unwrap();
+        assert_eq!(containers.len(), images_to_pull.len());
+        assert_eq!(containers[1]["name"], "image-1");
+        assert_eq!(containers[1]["image"].as_str(), Some(images_to_pull[1].as_str()));
+        assert_eq!(pod["spec"]["restartPolicy"], "Never");
+    }
+
+    #[test]
+    fn cni_daemonsets_match_their_manifests() {
+        for &(name, cni) in &[("flannel", Cni::Flannel), ("calico", Cni::Calico), ("canal", Cni::Canal)] {
+            let daemonset = CNI_DAEMONSETS.iter().find(|&&(other, _)| other == cni).unwrap().1;
+            let config = config(&format!("[net]\ncni = \"{}\"\n", name));
+            let k8s = K8s::phase(Phase::DeployControlPlane, &config);
+            let definition = format!("kind: DaemonSet\nmetadata:\n  name: {}\n", daemonset);
+            assert!(k8s.control_plane_manifests().iter().any(
+                |&(_, manifest)| manifest.contains(&definition),
+            ));
+        }
+    }
+
+    #[test]
+    fn documents_are_split_on_separators() {
+        let documents = manifest_documents(MANIFESTS);
+        assert_eq!(documents.len(), 2);
+        assert!(documents[0].starts_with("apiVersion: v1\n"));
+    }
+
+    #[test]
+    fn identities_are_parsed() {
+        let documents = manifest_documents(MANIFESTS);
+        let identity = manifest_identity(&documents[1]).unwrap();
+        assert_eq!(identity.kind, "Deployment");
+        assert_eq!(identity.namespace, Some("kube-system".to_string()));
+        assert_eq!(identity.name, "coredns");
+        assert!(manifest_identity("apiVersion: v1\nkind: List\n").is_none());
+        assert_eq!(
+            manifest_identity("kind: ClusterRole\nmetadata:\n  name: system:coredns\n")
+                .unwrap()
+                .namespace,
+            None
+        );
+    }
+
+    #[test]
+    fn patches_target_kind_namespace_and_name() {
+        let config = config(
+            r#"
+[[patches]]
+kind = "Deployment"
+name = "coredns"
+type = "json"
+patch = '[{"op": "replace", "path": "/spec/replicas", "value": 3}]'
+
+[[patches]]
+kind = "Deployment"
+namespace = "default"
+name = "coredns"
+type = "merge"
+patch = "spec: {replicas: 5}"
+"#,
+        );
+        let k8s = K8s::phase(Phase::DeployControlPlane, &config);
+        let patched = k8s.patch_manifest(MANIFESTS.to_string()).unwrap();
+        let documents = manifest_documents(&patched);
+        assert_eq!(documents.len(), 2);
+        assert!(documents[0].contains("kind: ServiceAccount"));
+        let deployment: Value = serde_yaml::from_str(&documents[1]).unwrap();
+        assert_eq!(deployment["spec"]["replicas"], json!(3));
+        assert_eq!(deployment["spec"]["template"]["spec"]["containers"][0]["name"], json!("coredns"));
+    }
+
+    #[test]
+    fn failing_patches_are_errors() {
+        let config = config(
+            r#"
+[[patches]]
+kind = "Deployment"
+name = "coredns"
+type = "json"
+patch = '[{"op": "remove", "path": "/spec/missing"}]'
+"#,
+        );
+        let k8s = K8s::phase(Phase::DeployControlPlane, &config);
+        assert!(k8s.patch_manifest(MANIFESTS.to_string()).is_err());
+    }
+
+    #[test]
+    fn duplicates_are_reported_once() {
+        let flags: Vec<String> = ["--v", "--feature-gates", "--v", "--v", "--kubeconfig"]
+            .iter()
+            .map(|flag| flag.to_string())
+            .collect();
+        assert_eq!(duplicate_flags(&flags), vec!["--v".to_string()]);
+        assert!(duplicate_flags(&flags[..2]).is_empty());
+    }
+
+    #[test]
+    fn flags_are_collected_per_container() {
+        let document: Value = serde_yaml::from_str(
+            r#"
+kind: DaemonSet
+spec:
+  template:
+    spec:
+      initContainers:
+      - name: setup
+        command: [sh, -c, "--not-a-flag"]
+      containers:
+      - name: kube-apiserver
+        command:
+        - ./hyperkube
+        - apiserver
+        - --secure-port=6443
+        - --v
+        args:
+        - '--audit-log-maxage=30'
+      - name: sidecar
+        args:
+        - --secure-port=8443
+"#,
+        ).unwrap();
+        assert_eq!(
+            container_flags(&document, "kube-apiserver"),
+            vec!["--secure-port", "--v", "--audit-log-maxage"]
+        );
+        assert_eq!(container_flags(&document, "sidecar"), vec!["--secure-port"]);
+        let pod: Value = serde_yaml::from_str(
+            "kind: Pod\nspec:\n  containers:\n  - name: kube-scheduler\n    command: [--leader-elect=true]\n",
+        ).unwrap();
+        assert_eq!(container_flags(&pod, "kube-scheduler"), vec!["--leader-elect"]);
+    }
+
+    #[test]
+    fn extra_args_conflicting_with_builtin_flags_are_errors() {
+        let config = config(
+            "[controller_manager.extra_args]\nservice-cluster-ip-range = \"10.0.0.0/24\"\n",
+        );
+        let k8s = K8s::phase(Phase::DeployControlPlane, &config);
+        let template = "kind: Pod\nspec:\n  containers:\n  - name: kube-controller-manager\n    command:\n    - --service-cluster-ip-range=10.3.0.0/24\n{{#each controller_manager_args}}\n    - {{{this}}}\n{{/each}}\n";
+        assert!(k8s.check_container_flags("kube-controller-manager", template, &json!({})).is_err());
+        assert!(
+            k8s.check_container_flags("kube-scheduler", template, &json!({"controller_manager_args": []}))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn strategic_patches_are_applied_in_a_dry_run() {
+        let mut config = config(
+            r#"
+[[patches]]
+kind = "Deployment"
+name = "coredns"
+patch = "spec: {replicas: 3}"
+"#,
+        );
+        config.output_dir = Some(PathBuf::from("/nonexistent"));
+        let k8s = K8s::phase(Phase::DeployControlPlane, &config);
+        match k8s.patch_manifest(MANIFESTS.to_string()) {
+            Ok(manifest) => assert!(manifest.contains("replicas: 3")),
+            Err(K8sError::PatchError { target, reason }) => {
+                assert_eq!(target, "Deployment kube-system/coredns");
+                assert_eq!(reason, "patches.0: kubectl patch --local failed");
+            }
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn addons_get_the_manifest_context_without_secrets() {
+        let context = K8s::addon_values(json!({
+            "apiserver_host": "master-1",
+            "apiserver_port": 6443,
+            "ca_crt": "certificate",
+            "ca_key": "key",
+            "image_pull_secret": "secret",
+        }));
+        assert_eq!(
+            template::render("server: https://{{apiserver_host}}:{{apiserver_port}}", &context)
+                .unwrap(),
+            "server: https://master-1:6443"
+        );
+        assert_eq!(context["ca_crt"], "certificate");
+        for key in ADDON_EXCLUDED_KEYS {
+            assert!(context.get(*key).is_none());
+        }
+    }
+
+    #[test]
+    fn a_single_apiserver_is_not_restarted_for_new_encryption_keys() {
+        assert_eq!(apiserver_daemonset_status(""), (0, String::new()));
+        assert_eq!(apiserver_daemonset_status("1 "), (1, String::new()));
+        assert_eq!(apiserver_daemonset_status("1 old"), (1, "old".to_string()));
+        assert_eq!(rollout_checksum("new", 0, ""), "new");
+        assert_eq!(rollout_checksum("new", 1, "old"), "old");
+        assert_eq!(rollout_checksum("new", 1, ""), "");
+        assert_eq!(rollout_checksum("new", 1, "new"), "new");
+        assert_eq!(rollout_checksum("new", 3, "old"), "new");
+    }
+}
@@ -38,15 +38,32 @@ impl<'a> Kubectl<'a> {
         stdin: Option<&str>,
         kubeconfig_type: Option<&KubeconfigType>,
     ) -> Result<(), KubectlError> {
+        self.output(args, stdin, kubeconfig_type).map(|_| ())
+    }
+
+    pub fn output(
+        &self,
+        args: &[&str],
+        stdin: Option<&str>,
+        kubeconfig_type: Option<&KubeconfigType>,
+    ) -> Result<String, KubectlError> {
         let new_arg = format!(
             "--kubeconfig={}",
             &self.k8s.kubeconfig_path(kubeconfig_type).display()
         );
         let mut args = Vec::from(args);
         args.insert(0, new_arg.as_str());
+        self.execute(&args, stdin)
+    }
+
+    pub fn local(&self, args: &[&str], stdin: Option<&str>) -> Result<String, KubectlError> {
+        self.execute(args, stdin)
+    }
+
+    fn execute(&self, args: &[&str], stdin: Option<&str>) -> Result<String, KubectlError> {
         let output = if let Some(stdin) = stdin {
             let mut command = Command::new("kubectl")
-                .args(&args)
+                .args(args)
                 .stdin(Stdio::piped())
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
@@ -58,15 +75,16 @@ impl<'a> Kubectl<'a> {
             command.wait_with_output()?
         } else {
             Command::new("kubectl")
-                .args(&args)
+                .args(args)
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
                 .output()?
         };
-        debug!("stdout: {}", String::from_utf8(output.stdout).unwrap());
-        debug!("stderr: {}", String::from_utf8(output.stderr).unwrap());
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        debug!("stdout: {}", stdout);
+        debug!("stderr: {}", String::from_utf8_lossy(&output.stderr));
         if output.status.success() {
-            return Ok(());
+            return Ok(stdout);
         };
         Err(KubectlError::UnknownError)
     }
@@ -2,10 +2,12 @@ extern crate base64;
 extern crate clap;
 extern crate dbus;
 extern crate openssl;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
+extern crate serde_yaml;
 extern crate toml;
 extern crate uuid;
 extern crate handlebars;
@@ -17,25 +19,86 @@ extern crate reqwest;
 extern crate rouille;
 
 mod pki;
+mod profile;
 mod etcd;
+mod encryption;
+mod images;
 mod k8s;
+mod manifests;
+mod net;
+mod patch;
 mod kubectl;
+mod migration;
 mod config;
+mod inventory;
+mod reset;
 mod resources;
+mod security;
 mod server;
+mod state;
 mod system;
 mod systemd;
+mod template;
+mod validation;
+mod workflow;
 
-use clap::{Arg, App, SubCommand};
+use clap::{Arg, App, ArgMatches, SubCommand};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 
 use config::Config;
-use etcd::Etcd;
+use reset::Reset;
 use server::Server;
 use k8s::K8s;
+use workflow::{Step, Workflow};
+
+fn config_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("config")
+        .short("c")
+        .long("config")
+        .value_name("FILE")
+        .help("Configuration file path")
+        .takes_value(true)
+        .required(true)
+}
+
+fn load_config(matches: &ArgMatches, validate: bool) -> Config {
+    let config = match Config::from_file(matches.value_of("config").unwrap()) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    for warning in &config.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if validate {
+        if let Err(error) = config.validate() {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+    config
+}
+
+fn print_config(config: &Config) {
+    match config.to_toml() {
+        Ok(config) => print!("{}", config),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
 
 fn main() {
     env_logger::init();
 
+    let phases = Step::names();
+
     let matches = App::new("kubeception")
         .version(option_env!("CARGO_PKG_VERSION").unwrap())
         .author(option_env!("CARGO_PKG_AUTHORS").unwrap())
@@ -43,16 +106,48 @@ fn main() {
         .subcommand(
             SubCommand::with_name("bootstrap")
                 .about("Bootstraps a kubernetes cluster")
+                .arg(config_arg())
+                .arg(Arg::with_name("resume").long("resume").help(
+                    "Continue from the phase that failed on the previous run",
+                ))
                 .arg(
-                    Arg::with_name("config")
-                        .short("c")
-                        .long("config")
-                        .value_name("FILE")
-                        .help("Configuration file path")
+                    Arg::with_name("skip-phases")
+                        .long("skip-phases")
+                        .value_name("PHASES")
+                        .help("Comma separated list of phases to skip")
                         .takes_value(true)
-                        .required(true),
+                        .use_delimiter(true)
+                        .possible_values(&phases),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help(
+                            "Render everything into the output directory instead of the host",
+                        )
+                        .requires("output-dir")
+                        .conflicts_with_all(&["resume", "skip-phases"]),
+                )
+                .arg(
+                    Arg::with_name("output-dir")
+                        .long("output-dir")
+                        .value_name("DIR")
+                        .help("Output directory for --dry-run")
+                        .takes_value(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("phase")
+                .about("Runs a single phase of the bootstrap")
+                .arg(
+                    Arg::with_name("name")
+                        .value_name("NAME")
+                        .help("Phase to run")
+                        .required(true)
+                        .possible_values(&phases),
+                )
+                .arg(config_arg()),
+        )
         .subcommand(
             SubCommand::with_name("serve")
                 .about(
@@ -70,15 +165,7 @@ fn main() {
         .subcommand(
             SubCommand::with_name("join")
                 .about("Joins a node to an already bootstrapped cluster")
-                .arg(
-                    Arg::with_name("config")
-                        .short("c")
-                        .long("config")
-                        .value_name("FILE")
-                        .help("Configuration file path")
-                        .takes_value(true)
-                        .required(true),
-                )
+                .arg(config_arg())
                 .arg(
                     Arg::with_name("url")
                         .short("u")
@@ -89,20 +176,302 @@ fn main() {
                         .required(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("reset")
+                .about("Reverts the changes made by bootstrap or join on this node")
+                .arg(config_arg())
+                .arg(Arg::with_name("wipe-pki").long("wipe-pki").help(
+                    "Also remove the keys and certificates generated by kubeception",
+                ))
+                .arg(Arg::with_name("wipe-etcd-data").long("wipe-etcd-data").help(
+                    "Also remove the etcd data directory",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Configuration file utilities")
+                .subcommand(
+                    SubCommand::with_name("validate")
+                        .about("Validates a configuration file")
+                        .arg(config_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("migrate")
+                        .about(
+                            "Migrates a configuration file to the current apiVersion",
+                        )
+                        .arg(
+                            Arg::with_name("in")
+                                .long("in")
+                                .value_name("FILE")
+                                .help("Configuration file to migrate")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("out")
+                                .long("out")
+                                .value_name("FILE")
+                                .help("Path where the migrated configuration is written")
+                                .takes_value(true)
+                                .required(true),
+                        ),
+                )
+                .subcommand(SubCommand::with_name("print-defaults").about(
+                    "Prints the default configuration",
+                ))
+                .subcommand(
+                    SubCommand::with_name("view")
+                        .about(
+                            "Prints the effective configuration, with defaults applied",
+                        )
+                        .arg(config_arg()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("images")
+                .about("Container image utilities")
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("Lists every image the configuration uses")
+                        .arg(config_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about(
+                            "Exports the mapping from upstream images to [images] registry for a mirroring tool",
+                        )
+                        .arg(config_arg())
+                        .arg(
+                            Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Path where the mapping is written")
+                                .takes_value(true)
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("manifests")
+                .about("Built-in manifest utilities")
+                .subcommand(
+                    SubCommand::with_name("dump")
+                        .about(
+                            "Writes every built-in template, as a starting point for [manifests] override_dir",
+                        )
+                        .arg(
+                            Arg::with_name("output-dir")
+                                .long("output-dir")
+                                .value_name("DIR")
+                                .help("Directory where the templates are written")
+                                .takes_value(true)
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("secrets")
+                .about("Secrets encryption utilities")
+                .subcommand(
+                    SubCommand::with_name("rotate-encryption-key")
+                        .about(
+                            "Adds a new encryption key, rewrites all secrets with it and removes the previous keys",
+                        )
+                        .arg(config_arg()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("kubeconfig")
+                .about("Kubeconfig utilities")
+                .subcommand(
+                    SubCommand::with_name("oidc")
+                        .about("Prints a kubeconfig that authenticates with the [auth.oidc] provider")
+                        .arg(config_arg())
+                        .arg(
+                            Arg::with_name("user")
+                                .long("user")
+                                .value_name("NAME")
+                                .help("User name in the kubeconfig")
+                                .takes_value(true)
+                                .default_value("oidc"),
+                        )
+                        .arg(
+                            Arg::with_name("server")
+                                .long("server")
+                                .value_name("URL")
+                                .help("Apiserver URL, defaults to this host")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("security")
+                .about("Cluster security utilities")
+                .subcommand(
+                    SubCommand::with_name("audit")
+                        .about("Reports RBAC bindings that grant more than they should")
+                        .arg(config_arg()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dns")
+                .about("Cluster DNS utilities")
+                .subcommand(
+                    SubCommand::with_name("migrate")
+                        .about(
+                            "Deploys the [dns] provider on a running cluster and removes the other one",
+                        )
+                        .arg(config_arg()),
+                ),
+        )
         .get_matches();
 
     if let Some(matches) = matches.subcommand_matches("bootstrap") {
-        let config = Config::from_file(matches.value_of("config").unwrap());
-        Etcd::bootstrap(&config);
-        K8s::bootstrap(&config);
-        K8s::control_plane(&config);
-        K8s::kubelet(&config);
+        let mut config = load_config(matches, true);
+        if matches.is_present("dry-run") {
+            let output_dir = PathBuf::from(matches.value_of("output-dir").unwrap());
+            config.output_dir = Some(output_dir.clone());
+            if Workflow::dry_run(&config, &output_dir).is_err() {
+                process::exit(1);
+            }
+            return;
+        }
+        let skip_phases: Vec<&str> = matches
+            .values_of("skip-phases")
+            .map(|phases| phases.collect())
+            .unwrap_or_default();
+        if Workflow::bootstrap(&config, matches.is_present("resume"), &skip_phases).is_err() {
+            process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("phase") {
+        let config = load_config(matches, true);
+        let step = Step::from_name(matches.value_of("name").unwrap()).unwrap();
+        if Workflow::phase(&config, step).is_err() {
+            process::exit(1);
+        }
     } else if let Some(matches) = matches.subcommand_matches("serve") {
         let kubeconfig_path = matches.value_of("kubeconfig").unwrap();
         Server::run(kubeconfig_path.to_string());
     } else if let Some(matches) = matches.subcommand_matches("join") {
-        let config = Config::from_file(matches.value_of("config").unwrap());
+        let config = load_config(matches, true);
         let url = matches.value_of("url").unwrap();
-        K8s::join(&config, &String::from(url));
+        if let Err(error) = K8s::join(&config, &String::from(url)) {
+            eprintln!("{:?}", error);
+            process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("reset") {
+        let config = load_config(matches, false);
+        if let Err(error) = config.profile() {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        if let Err(error) = Reset::run(
+            &config,
+            matches.is_present("wipe-pki"),
+            matches.is_present("wipe-etcd-data"),
+        )
+        {
+            eprintln!("{:?}", error);
+            process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("config") {
+        if let Some(matches) = matches.subcommand_matches("validate") {
+            load_config(matches, true);
+            println!("configuration is valid");
+        } else if let Some(matches) = matches.subcommand_matches("migrate") {
+            match Config::migrate(
+                matches.value_of("in").unwrap(),
+                matches.value_of("out").unwrap(),
+            ) {
+                Ok(warnings) => {
+                    for warning in warnings {
+                        eprintln!("warning: {}", warning);
+                    }
+                }
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            }
+        } else if matches.subcommand_matches("print-defaults").is_some() {
+            print_config(&Config::default());
+        } else if let Some(matches) = matches.subcommand_matches("view") {
+            print_config(&load_config(matches, false));
+        }
+    } else if let Some(matches) = matches.subcommand_matches("images") {
+        if let Some(matches) = matches.subcommand_matches("list") {
+            for (_, image) in images::images(&load_config(matches, true)) {
+                println!("{}", image);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("export") {
+            let config = load_config(matches, true);
+            if config.images.registry.is_none() {
+                eprintln!("images.registry is not set; there is no mirror to export to");
+                process::exit(1);
+            }
+            if let Err(error) = fs::write(
+                matches.value_of("output").unwrap(),
+                images::mirror_mapping(&config),
+            )
+            {
+                eprintln!("could not write image mapping: {}", error);
+                process::exit(1);
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("manifests") {
+        if let Some(matches) = matches.subcommand_matches("dump") {
+            if let Err(error) = manifests::dump(Path::new(matches.value_of("output-dir").unwrap())) {
+                eprintln!("could not write manifests: {}", error);
+                process::exit(1);
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("secrets") {
+        if let Some(matches) = matches.subcommand_matches("rotate-encryption-key") {
+            if let Err(error) = K8s::rotate_encryption_key(&load_config(matches, true)) {
+                eprintln!("could not rotate the encryption key: {:?}", error);
+                process::exit(1);
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("kubeconfig") {
+        if let Some(matches) = matches.subcommand_matches("oidc") {
+            match K8s::oidc_kubeconfig(
+                &load_config(matches, true),
+                matches.value_of("user").unwrap(),
+                matches.value_of("server"),
+            ) {
+                Ok(kubeconfig) => print!("{}", kubeconfig),
+                Err(error) => {
+                    eprintln!("could not render the kubeconfig: {:?}", error);
+                    process::exit(1);
+                }
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("security") {
+        if let Some(matches) = matches.subcommand_matches("audit") {
+            match K8s::security_audit(&load_config(matches, true)) {
+                Ok(ref findings) if findings.is_empty() => {
+                    println!("no over-privileged bindings found");
+                }
+                Ok(findings) => {
+                    for finding in findings {
+                        println!("{}", finding);
+                    }
+                    process::exit(1);
+                }
+                Err(error) => {
+                    eprintln!("could not audit the cluster: {:?}", error);
+                    process::exit(1);
+                }
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("dns") {
+        if let Some(matches) = matches.subcommand_matches("migrate") {
+            if let Err(error) = K8s::migrate_dns(&load_config(matches, true)) {
+                eprintln!("could not migrate the cluster DNS: {:?}", error);
+                process::exit(1);
+            }
+        }
     }
 }
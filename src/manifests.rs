@@ -0,0 +1,107 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use config::Config;
+use resources::bootstrap::etcd::ETCD_BOOTSTRAP_CONFIG;
+use resources::bootstrap::k8s::{ALL_MANIFESTS as BOOTSTRAP_MANIFESTS, KUBECONFIG};
+use resources::bootstrap::kubelet::KUBELET_CONFIG as BOOTSTRAP_KUBELET_CONFIG;
+use resources::control_plane::{audit, calico, canal, dns, flannel, k8s, kubeception, oidc};
+use resources::control_plane::kubelet::KUBELET_CONFIG as CONTROL_PLANE_KUBELET_CONFIG;
+
+pub const BOOTSTRAP_ETCD_CONFIG: &'static str = "bootstrap/etcd.env";
+pub const BOOTSTRAP_KUBELET: &'static str = "bootstrap/kubelet.env";
+pub const BOOTSTRAP_KUBECONFIG: &'static str = "bootstrap/kubeconfig.yaml";
+pub const KUBELET: &'static str = "kubelet.env";
+pub const AUDIT_POLICY: &'static str = "audit-policy.yaml";
+pub const OIDC_KUBECONFIG: &'static str = "oidc-kubeconfig.yaml";
+
+pub fn bootstrap_name(name: &str) -> String {
+    format!("bootstrap/{}.yaml", name)
+}
+
+pub fn control_plane_name(name: &str) -> String {
+    format!("{}.yaml", name)
+}
+
+pub fn builtin() -> Vec<(String, &'static str)> {
+    let mut manifests = vec![
+        (BOOTSTRAP_ETCD_CONFIG.to_string(), ETCD_BOOTSTRAP_CONFIG),
+        (BOOTSTRAP_KUBELET.to_string(), BOOTSTRAP_KUBELET_CONFIG),
+        (BOOTSTRAP_KUBECONFIG.to_string(), KUBECONFIG),
+        (KUBELET.to_string(), CONTROL_PLANE_KUBELET_CONFIG),
+        (AUDIT_POLICY.to_string(), audit::AUDIT_POLICY),
+        (OIDC_KUBECONFIG.to_string(), oidc::OIDC_KUBECONFIG),
+        (control_plane_name("image-pull-secret"), k8s::IMAGE_PULL_SECRET),
+    ];
+    for &(name, manifest) in BOOTSTRAP_MANIFESTS {
+        manifests.push((bootstrap_name(name), manifest));
+    }
+    for &(name, manifest) in flannel::ALL_MANIFESTS
+        .iter()
+        .chain(calico::CUSTOM_RESOURCE_DEFINITIONS)
+        .chain(calico::ALL_MANIFESTS)
+        .chain(canal::ALL_MANIFESTS)
+        .chain(k8s::ALL_MANIFESTS)
+        .chain(dns::KUBE_DNS_MANIFESTS)
+        .chain(dns::COREDNS_MANIFESTS)
+        .chain(kubeception::ALL_MANIFESTS)
+    {
+        let name = control_plane_name(name);
+        if !manifests.iter().any(|&(ref known, _)| *known == name) {
+            manifests.push((name, manifest));
+        }
+    }
+    manifests
+}
+
+pub fn template(config: &Config, name: &str, builtin: &'static str) -> io::Result<String> {
+    if let Some(ref override_dir) = config.manifests.override_dir {
+        let path = Path::new(override_dir).join(name);
+        if path.is_file() {
+            info!("using {} instead of the built-in {}", path.display(), name);
+            return fs::read_to_string(path);
+        }
+    }
+    Ok(builtin.to_string())
+}
+
+pub fn unknown_overrides(override_dir: &str) -> Vec<String> {
+    let known = builtin();
+    let mut unknown = Vec::new();
+    for directory in &["", "bootstrap"] {
+        let entries = match fs::read_dir(Path::new(override_dir).join(directory)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(_) => continue,
+            };
+            if !path.is_file() {
+                continue;
+            }
+            let name = match path.strip_prefix(override_dir) {
+                Ok(name) => name.to_string_lossy().into_owned(),
+                Err(_) => continue,
+            };
+            if !known.iter().any(|&(ref known, _)| *known == name) {
+                unknown.push(name);
+            }
+        }
+    }
+    unknown.sort();
+    unknown
+}
+
+pub fn dump(output_dir: &Path) -> io::Result<()> {
+    for (name, manifest) in builtin() {
+        let path = output_dir.join(&name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, manifest.trim_left_matches('\n'))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use toml::Value;
+use toml::value::Table;
+
+use config::ConfigError;
+
+pub const API_VERSION: &'static str = "kubeception/v1alpha1";
+
+type Migration = fn(&mut Table, &mut Vec<String>);
+
+const MIGRATIONS: &'static [(Option<&'static str>, &'static str, Migration)] =
+    &[(None, API_VERSION, keep_kube_dns)];
+
+fn version_name(api_version: Option<&str>) -> &str {
+    api_version.unwrap_or("the layout without apiVersion")
+}
+
+pub fn migrate(config: &mut Value) -> Result<Vec<String>, ConfigError> {
+    let mut warnings = Vec::new();
+    let table = match config.as_table_mut() {
+        Some(table) => table,
+        None => return Err(ConfigError::ParseError("expected a table".to_string())),
+    };
+    let mut api_version = match table.get("apiVersion") {
+        Some(&Value::String(ref api_version)) => Some(api_version.clone()),
+        Some(_) => {
+            return Err(ConfigError::VersionError(
+                "apiVersion must be a string".to_string(),
+            ))
+        }
+        None => None,
+    };
+    while api_version.as_ref().map(|api_version| api_version.as_str()) != Some(API_VERSION) {
+        let &(from, to, migration) = match MIGRATIONS.iter().find(|&&(from, _, _)| {
+            from == api_version.as_ref().map(|api_version| api_version.as_str())
+        }) {
+            Some(migration) => migration,
+            None => {
+                return Err(ConfigError::VersionError(format!(
+                    "unsupported apiVersion {}; the current one is {}",
+                    version_name(api_version.as_ref().map(|api_version| api_version.as_str())),
+                    API_VERSION
+                )))
+            }
+        };
+        warnings.push(format!(
+            "migrating configuration from {} to {}",
+            version_name(from),
+            to
+        ));
+        migration(table, &mut warnings);
+        api_version = Some(to.to_string());
+    }
+    table.insert("apiVersion".to_string(), Value::String(API_VERSION.to_string()));
+    Ok(warnings)
+}
+
+fn keep_kube_dns(config: &mut Table, warnings: &mut Vec<String>) {
+    let dns = config
+        .entry("dns".to_string())
+        .or_insert_with(|| Value::Table(Table::new()));
+    if let Value::Table(ref mut dns) = *dns {
+        if !dns.contains_key("provider") {
+            warnings.push(
+                "dns.provider now defaults to coredns; kept kube-dns, set dns.provider = \"coredns\" to switch"
+                    .to_string(),
+            );
+            dns.insert("provider".to_string(), Value::String("kube-dns".to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use toml;
+    use toml::Value;
+
+    use super::*;
+
+    fn migrated(contents: &str) -> (Value, Vec<String>) {
+        let mut value: Value = toml::from_str(contents).unwrap();
+        let warnings = migrate(&mut value).unwrap();
+        (value, warnings)
+    }
+
+    #[test]
+    fn legacy_layout_keeps_kube_dns() {
+        let (value, warnings) = migrated("[net]\ncluster_cidr = \"10.0.0.0/16\"\n");
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(value["apiVersion"].as_str(), Some(API_VERSION));
+        assert_eq!(value["dns"]["provider"].as_str(), Some("kube-dns"));
+        assert_eq!(value["net"]["cluster_cidr"].as_str(), Some("10.0.0.0/16"));
+    }
+
+    #[test]
+    fn current_api_version_is_untouched() {
+        let (value, warnings) = migrated(&format!("apiVersion = \"{}\"\n", API_VERSION));
+        assert!(warnings.is_empty());
+        assert!(value.get("dns").is_none());
+    }
+
+    #[test]
+    fn explicit_dns_provider_is_kept() {
+        let (value, warnings) = migrated("[dns]\nprovider = \"coredns\"\n");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(value["dns"]["provider"].as_str(), Some("coredns"));
+    }
+
+    #[test]
+    fn nodeport_is_not_rewritten() {
+        let (value, _) = migrated(
+            "[kubeception]\nnodeport = \"30000\"\n",
+        );
+        assert_eq!(value["kubeception"]["nodeport"].as_str(), Some("30000"));
+    }
+
+    #[test]
+    fn unknown_api_version_is_rejected() {
+        let mut value: Value = toml::from_str("apiVersion = \"kubeception/v2\"\n").unwrap();
+        assert!(migrate(&mut value).is_err());
+    }
+}
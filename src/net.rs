@@ -0,0 +1,94 @@
+use std::net::IpAddr;
+
+pub struct Cidr {
+    address: u128,
+    prefix: u32,
+    width: u32,
+}
+
+fn address_bits(address: &IpAddr) -> (u128, u32) {
+    match *address {
+        IpAddr::V4(address) => (u128::from(u32::from(address)), 32),
+        IpAddr::V6(address) => (u128::from(address), 128),
+    }
+}
+
+impl Cidr {
+    pub fn parse(cidr: &str) -> Option<Cidr> {
+        let mut parts = cidr.splitn(2, '/');
+        let address: IpAddr = parts.next()?.parse().ok()?;
+        let prefix: u32 = parts.next()?.parse().ok()?;
+        let (address, width) = address_bits(&address);
+        if prefix > width {
+            return None;
+        }
+        Some(Cidr {
+            address: address,
+            prefix: prefix,
+            width: width,
+        })
+    }
+
+    fn mask(&self, prefix: u32) -> u128 {
+        if prefix == 0 {
+            return 0;
+        }
+        let width_mask = if self.width == 128 {
+            !0u128
+        } else {
+            (1u128 << self.width) - 1
+        };
+        (!0u128 << (self.width - prefix)) & width_mask
+    }
+
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        let (address, width) = address_bits(address);
+        width == self.width && address & self.mask(self.prefix) == self.address & self.mask(self.prefix)
+    }
+
+    pub fn overlaps(&self, other: &Cidr) -> bool {
+        if self.width != other.width {
+            return false;
+        }
+        let mask = self.mask(self.prefix.min(other.prefix));
+        self.address & mask == other.address & mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert!(Cidr::parse("10.3.0.0/24").is_some());
+        assert!(Cidr::parse("0.0.0.0/0").is_some());
+        assert!(Cidr::parse("fd00::/8").is_some());
+        assert!(Cidr::parse("10.3.0.0/33").is_none());
+        assert!(Cidr::parse("fd00::/129").is_none());
+        assert!(Cidr::parse("10.3.0.0").is_none());
+        assert!(Cidr::parse("10.3.0/24").is_none());
+        assert!(Cidr::parse("10.3.0.0/x").is_none());
+    }
+
+    #[test]
+    fn contains() {
+        let cidr = Cidr::parse("10.3.0.0/24").unwrap();
+        assert!(cidr.contains(&"10.3.0.10".parse().unwrap()));
+        assert!(!cidr.contains(&"10.3.1.10".parse().unwrap()));
+        assert!(!cidr.contains(&"::ffff:10.3.0.10".parse().unwrap()));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(&"192.168.1.1".parse().unwrap()));
+        assert!(Cidr::parse("fd00::/8").unwrap().contains(&"fd12::1".parse().unwrap()));
+        assert!(Cidr::parse("fd00::1/128").unwrap().contains(&"fd00::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn overlaps() {
+        let cluster = Cidr::parse("10.2.0.0/16").unwrap();
+        assert!(cluster.overlaps(&Cidr::parse("10.2.3.0/24").unwrap()));
+        assert!(Cidr::parse("10.2.3.0/24").unwrap().overlaps(&cluster));
+        assert!(!cluster.overlaps(&Cidr::parse("10.3.0.0/24").unwrap()));
+        assert!(cluster.overlaps(&Cidr::parse("0.0.0.0/0").unwrap()));
+        assert!(!cluster.overlaps(&Cidr::parse("::/0").unwrap()));
+    }
+}
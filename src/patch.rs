@@ -0,0 +1,246 @@
+use serde_json::Value;
+use serde_json::map::Map;
+
+fn tokens(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(format!("{} is not a JSON pointer", pointer));
+    }
+    Ok(
+        pointer[1..]
+            .split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+fn pointee<'a>(value: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    match tokens.split_first() {
+        None => Some(value),
+        Some((token, rest)) => {
+            let child = match *value {
+                Value::Object(ref mut map) => map.get_mut(token),
+                Value::Array(ref mut array) => {
+                    match token.parse::<usize>() {
+                        Ok(index) => array.get_mut(index),
+                        Err(_) => None,
+                    }
+                }
+                _ => None,
+            };
+            match child {
+                Some(child) => pointee(child, rest),
+                None => None,
+            }
+        }
+    }
+}
+
+fn array_index(token: &str, len: usize, insert: bool) -> Option<usize> {
+    if insert && token == "-" {
+        return Some(len);
+    }
+    match token.parse::<usize>() {
+        Ok(index) if index < len || (insert && index == len) => Some(index),
+        _ => None,
+    }
+}
+
+fn add(target: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let tokens = tokens(path)?;
+    let (last, parent) = match tokens.split_last() {
+        Some(split) => split,
+        None => {
+            *target = value;
+            return Ok(());
+        }
+    };
+    match pointee(target, parent) {
+        Some(&mut Value::Object(ref mut map)) => {
+            map.insert(last.clone(), value);
+            Ok(())
+        }
+        Some(&mut Value::Array(ref mut array)) => {
+            match array_index(last, array.len(), true) {
+                Some(index) => {
+                    array.insert(index, value);
+                    Ok(())
+                }
+                None => Err(format!("{} is not a valid array index", path)),
+            }
+        }
+        _ => Err(format!("the parent of {} does not exist", path)),
+    }
+}
+
+fn remove(target: &mut Value, path: &str) -> Result<Value, String> {
+    let tokens = tokens(path)?;
+    let (last, parent) = match tokens.split_last() {
+        Some(split) => split,
+        None => return Err("the whole document cannot be removed".to_string()),
+    };
+    let removed = match pointee(target, parent) {
+        Some(&mut Value::Object(ref mut map)) => map.remove(last),
+        Some(&mut Value::Array(ref mut array)) => {
+            array_index(last, array.len(), false).map(|index| array.remove(index))
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("{} does not exist", path))
+}
+
+fn operation_value(operation: &Value) -> Result<Value, String> {
+    match operation.get("value") {
+        Some(value) => Ok(value.clone()),
+        None => Err("value is missing".to_string()),
+    }
+}
+
+fn operation_pointer<'a>(operation: &'a Value, key: &str) -> Result<&'a str, String> {
+    operation[key].as_str().ok_or_else(
+        || format!("{} is missing", key),
+    )
+}
+
+pub fn apply(target: &mut Value, operations: &[Value]) -> Result<(), String> {
+    for operation in operations {
+        let path = operation_pointer(operation, "path")?;
+        match operation["op"].as_str() {
+            Some("add") => add(target, path, operation_value(operation)?)?,
+            Some("remove") => {
+                remove(target, path)?;
+            }
+            Some("replace") => {
+                remove(target, path)?;
+                add(target, path, operation_value(operation)?)?;
+            }
+            Some("move") => {
+                let value = remove(target, operation_pointer(operation, "from")?)?;
+                add(target, path, value)?;
+            }
+            Some("copy") => {
+                let from = operation_pointer(operation, "from")?;
+                let value = match pointee(target, &tokens(from)?) {
+                    Some(value) => value.clone(),
+                    None => return Err(format!("{} does not exist", from)),
+                };
+                add(target, path, value)?;
+            }
+            Some("test") => {
+                let expected = operation_value(operation)?;
+                if pointee(target, &tokens(path)?).map(|value| *value == expected) != Some(true) {
+                    return Err(format!("{} is not {}", path, expected));
+                }
+            }
+            Some(op) => return Err(format!("unknown operation {}", op)),
+            None => return Err("op is missing".to_string()),
+        }
+    }
+    Ok(())
+}
+
+pub fn merge(target: &mut Value, patch: &Value) {
+    let patch = match *patch {
+        Value::Object(ref patch) => patch,
+        _ => {
+            *target = patch.clone();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(ref mut target) = *target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(target: Value, operations: Value) -> Result<Value, String> {
+        let mut target = target;
+        apply(&mut target, operations.as_array().unwrap())?;
+        Ok(target)
+    }
+
+    #[test]
+    fn add_replace_and_remove() {
+        let target = json!({"spec": {"replicas": 1, "args": ["--a"]}});
+        assert_eq!(
+            applied(
+                target,
+                json!([
+                    {"op": "replace", "path": "/spec/replicas", "value": 3},
+                    {"op": "add", "path": "/spec/args/-", "value": "--b"},
+                    {"op": "add", "path": "/spec/args/0", "value": "--c"},
+                    {"op": "remove", "path": "/spec/args/1"},
+                    {"op": "add", "path": "/metadata", "value": {"a~b/c": true}},
+                ]),
+            ).unwrap(),
+            json!({"spec": {"replicas": 3, "args": ["--c", "--b"]}, "metadata": {"a~b/c": true}})
+        );
+    }
+
+    #[test]
+    fn escaped_pointers() {
+        let target = json!({"metadata": {"annotations": {"example.com/a~b": "x"}}});
+        assert_eq!(
+            applied(
+                target,
+                json!([
+                    {"op": "replace", "path": "/metadata/annotations/example.com~1a~0b", "value": "y"},
+                ]),
+            ).unwrap(),
+            json!({"metadata": {"annotations": {"example.com/a~b": "y"}}})
+        );
+    }
+
+    #[test]
+    fn move_copy_and_test() {
+        let target = json!({"a": {"b": 1}, "c": []});
+        assert_eq!(
+            applied(
+                target.clone(),
+                json!([
+                    {"op": "test", "path": "/a/b", "value": 1},
+                    {"op": "copy", "from": "/a/b", "path": "/c/0"},
+                    {"op": "move", "from": "/a", "path": "/d"},
+                ]),
+            ).unwrap(),
+            json!({"c": [1], "d": {"b": 1}})
+        );
+        assert!(applied(target, json!([{"op": "test", "path": "/a/b", "value": 2}])).is_err());
+    }
+
+    #[test]
+    fn missing_paths_fail() {
+        let target = json!({"spec": {"args": []}});
+        assert!(applied(target.clone(), json!([{"op": "remove", "path": "/spec/missing"}])).is_err());
+        assert!(applied(target.clone(), json!([{"op": "replace", "path": "/spec/args/0", "value": 1}])).is_err());
+        assert!(applied(target.clone(), json!([{"op": "add", "path": "/missing/key", "value": 1}])).is_err());
+        assert!(applied(target, json!([{"op": "add", "path": "/spec/args/2", "value": 1}])).is_err());
+    }
+
+    #[test]
+    fn merge_patches() {
+        let mut target = json!({"spec": {"replicas": 1, "paused": true, "args": ["--a"]}});
+        merge(
+            &mut target,
+            &json!({"spec": {"replicas": 2, "paused": null, "args": ["--b"]}, "metadata": {"name": "x"}}),
+        );
+        assert_eq!(
+            target,
+            json!({"spec": {"replicas": 2, "args": ["--b"]}, "metadata": {"name": "x"}})
+        );
+    }
+}
@@ -2,10 +2,12 @@ use openssl::rsa::Rsa;
 use openssl::pkey::PKey;
 
 use std;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fs::File;
 use std::path::PathBuf;
 use std::io::prelude::*;
+use std::net::IpAddr;
 
 use config::Config;
 
@@ -40,6 +42,7 @@ pub struct Certificate<'a> {
     o: String,
     cn: String,
     extra_ips: Vec<String>,
+    extra_names: Vec<String>,
     key: Key,
     ca: CaCertificate<'a>,
 }
@@ -86,7 +89,7 @@ impl Key {
         }
     }
 
-    fn key_path(&self) -> PathBuf {
+    pub fn key_path(&self) -> PathBuf {
         self.path.join(&self.name)
     }
 
@@ -124,27 +127,32 @@ impl<'a> CaCertificate<'a> {
         PathBuf::from(&self.config.secrets.path).join("ca.crt")
     }
 
+    fn host_cert_path(&self) -> PathBuf {
+        self.config.host_path(self.cert_path())
+    }
+
     pub fn key(&self) -> Key {
-        Key::new("ca.key", &self.config.secrets.path)
+        Key::new("ca.key", self.config.host_path(&self.config.secrets.path))
     }
 
     pub fn cert(&self) -> Result<X509, PKIError> {
-        let mut file = File::open(self.cert_path())?;
+        let mut file = File::open(self.host_cert_path())?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
         Ok(X509::from_pem(contents.as_bytes())?)
     }
 
     pub fn present(&self) -> Result<&Self, PKIError> {
-        if self.cert_path().exists() {
+        if self.host_cert_path().exists() {
             return Ok(self);
         }
-        let mut file = File::create(self.cert_path())?;
+        let mut file = File::create(self.host_cert_path())?;
         let cert = Certificate::create(
             &Uuid::new_v4().hyphenated().to_string(),
             &Uuid::new_v4().hyphenated().to_string(),
             &vec![],
-            &Key::new("ca.key", &self.config.secrets.path),
+            &vec![],
+            &self.key(),
             None,
         )?
             .to_pem()?;
@@ -160,6 +168,7 @@ impl<'a> Certificate<'a> {
         o: T,
         cn: T,
         extra_ips: Vec<String>,
+        extra_names: Vec<String>,
         key: Key,
         ca: CaCertificate,
     ) -> Certificate
@@ -173,6 +182,7 @@ impl<'a> Certificate<'a> {
             o: o.into(),
             cn: cn.into(),
             extra_ips: extra_ips,
+            extra_names: extra_names,
             key: key,
             ca: ca,
         }
@@ -182,10 +192,51 @@ impl<'a> Certificate<'a> {
         &self.key
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn cert_path(&self) -> PathBuf {
         self.path.join(&self.name)
     }
 
+    fn expected_names(&self) -> Result<(BTreeSet<Vec<u8>>, BTreeSet<String>), PKIError> {
+        let mut ips = BTreeSet::new();
+        for ip in Some("127.0.0.1").into_iter().chain(
+            self.extra_ips.iter().map(|ip| ip.as_str()),
+        )
+        {
+            match ip.parse::<IpAddr>() {
+                Ok(IpAddr::V4(ip)) => ips.insert(ip.octets().to_vec()),
+                Ok(IpAddr::V6(ip)) => ips.insert(ip.octets().to_vec()),
+                Err(_) => continue,
+            };
+        }
+        let mut names: BTreeSet<String> = self.extra_names.iter().cloned().collect();
+        names.insert(System::hostname()?);
+        Ok((ips, names))
+    }
+
+    pub fn current(&self) -> Result<bool, PKIError> {
+        if !self.cert_path().exists() {
+            return Ok(false);
+        }
+        let cert = X509::from_pem(self.cert()?.as_bytes())?;
+        let mut ips = BTreeSet::new();
+        let mut names = BTreeSet::new();
+        if let Some(subject_alternative_names) = cert.subject_alt_names() {
+            for subject_alternative_name in subject_alternative_names.iter() {
+                if let Some(ip) = subject_alternative_name.ipaddress() {
+                    ips.insert(ip.to_vec());
+                }
+                if let Some(name) = subject_alternative_name.dnsname() {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        Ok(self.expected_names()? == (ips, names))
+    }
+
     pub fn cert(&self) -> Result<String, PKIError> {
         let mut file = File::open(self.cert_path())?;
         let mut contents = String::new();
@@ -197,6 +248,7 @@ impl<'a> Certificate<'a> {
         o: &String,
         cn: &String,
         extra_ips: &Vec<String>,
+        extra_names: &Vec<String>,
         key: &Key,
         ca_cert: Option<&CaCertificate>,
     ) -> Result<X509, PKIError> {
@@ -269,6 +321,9 @@ impl<'a> Certificate<'a> {
             for extra_ip in extra_ips {
                 subject_alternative_name.ip(extra_ip);
             }
+            for extra_name in extra_names {
+                subject_alternative_name.dns(extra_name);
+            }
             let subject_alternative_name = subject_alternative_name.build(&builder.x509v3_context(
                 None,
                 None,
@@ -285,13 +340,20 @@ impl<'a> Certificate<'a> {
 
     pub fn present(&self) -> Result<&Self, PKIError> {
         if self.cert_path().exists() {
-            return Ok(self);
+            if self.current()? {
+                return Ok(self);
+            }
+            warn!(
+                "{} does not have the expected subject alternative names; issuing it again",
+                self.cert_path().display()
+            );
         }
         let mut file = File::create(self.cert_path())?;
         let cert = Certificate::create(
             &self.o,
             &self.cn,
             &self.extra_ips,
+            &self.extra_names,
             self.key.present()?,
             Some(self.ca.present()?),
         )?
@@ -0,0 +1,124 @@
+pub enum EnvironmentFormat {
+    Sysconfig,
+    SystemdDropIn,
+}
+
+pub struct Profile {
+    pub name: &'static str,
+    pub format: EnvironmentFormat,
+    pub kubelet_config_file: &'static str,
+    pub etcd_config_file: &'static str,
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for character in value.chars() {
+        match character {
+            '%' => escaped.push_str("%%"),
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+pub const PROFILES: &'static [Profile] = &[
+    Profile {
+        name: "kubic",
+        format: EnvironmentFormat::Sysconfig,
+        kubelet_config_file: "/etc/kubernetes/kubelet",
+        etcd_config_file: "/etc/sysconfig/etcd",
+    },
+    Profile {
+        name: "debian",
+        format: EnvironmentFormat::Sysconfig,
+        kubelet_config_file: "/etc/default/kubelet",
+        etcd_config_file: "/etc/default/etcd",
+    },
+    Profile {
+        name: "ubuntu",
+        format: EnvironmentFormat::Sysconfig,
+        kubelet_config_file: "/etc/default/kubelet",
+        etcd_config_file: "/etc/default/etcd",
+    },
+    Profile {
+        name: "fedora",
+        format: EnvironmentFormat::SystemdDropIn,
+        kubelet_config_file: "/etc/systemd/system/kubelet.service.d/10-kubeception.conf",
+        etcd_config_file: "/etc/systemd/system/etcd.service.d/10-kubeception.conf",
+    },
+];
+
+impl Profile {
+    pub fn names() -> Vec<&'static str> {
+        PROFILES.iter().map(|profile| profile.name).collect()
+    }
+
+    pub fn from_name(name: &str) -> Option<&'static Profile> {
+        PROFILES.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn is_drop_in(&self) -> bool {
+        match self.format {
+            EnvironmentFormat::SystemdDropIn => true,
+            EnvironmentFormat::Sysconfig => false,
+        }
+    }
+
+    pub fn environment_file(&self, environment: &str) -> String {
+        match self.format {
+            EnvironmentFormat::Sysconfig => environment.to_string(),
+            EnvironmentFormat::SystemdDropIn => {
+                let mut drop_in = String::from("[Service]\n");
+                for line in environment.lines() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    if let Some(separator) = line.find('=') {
+                        let (key, value) = line.split_at(separator);
+                        drop_in.push_str(&format!(
+                            "Environment=\"{}={}\"\n",
+                            escape(key),
+                            escape(value[1..].trim_matches('"'))
+                        ));
+                    }
+                }
+                drop_in
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sysconfig_is_kept_verbatim() {
+        let environment = "# kubelet\nKUBELET_ARGS=\"--v=2\"\n";
+        let profile = Profile::from_name("debian").unwrap();
+        assert_eq!(profile.environment_file(environment), environment);
+    }
+
+    #[test]
+    fn drop_in_skips_comments_and_blank_lines() {
+        let profile = Profile::from_name("fedora").unwrap();
+        assert_eq!(
+            profile.environment_file("# kubelet\n\nKUBELET_ARGS=\"--v=2 --node-ip=10.0.0.1\"\n"),
+            "[Service]\nEnvironment=\"KUBELET_ARGS=--v=2 --node-ip=10.0.0.1\"\n"
+        );
+    }
+
+    #[test]
+    fn drop_in_escapes_values() {
+        let profile = Profile::from_name("fedora").unwrap();
+        assert_eq!(
+            profile.environment_file("ARGS=--format=%s --label=a\\\"b\n"),
+            "[Service]\nEnvironment=\"ARGS=--format=%%s --label=a\\\\\\\"b\"\n"
+        );
+    }
+}
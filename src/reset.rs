@@ -0,0 +1,197 @@
+use std;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use config::{Config, ConfigError};
+use inventory::{FileKind, Inventory, InventoryError};
+use k8s::K8s;
+use state::{State, StateError};
+use workflow::failed;
+
+use systemd;
+use systemd::Systemd;
+
+pub struct Reset<'a> {
+    config: &'a Config,
+    wipe_pki: bool,
+    wipe_etcd_data: bool,
+}
+
+pub enum ResetError {
+    IncompleteError { failures: Vec<String> },
+    UnknownError,
+}
+
+impl From<std::io::Error> for ResetError {
+    fn from(_error: std::io::Error) -> ResetError {
+        ResetError::UnknownError
+    }
+}
+
+impl From<InventoryError> for ResetError {
+    fn from(_error: InventoryError) -> ResetError {
+        ResetError::UnknownError
+    }
+}
+
+impl From<systemd::SystemdError> for ResetError {
+    fn from(_error: systemd::SystemdError) -> ResetError {
+        ResetError::UnknownError
+    }
+}
+
+impl From<StateError> for ResetError {
+    fn from(_error: StateError) -> ResetError {
+        ResetError::UnknownError
+    }
+}
+
+impl From<ConfigError> for ResetError {
+    fn from(_error: ConfigError) -> ResetError {
+        ResetError::UnknownError
+    }
+}
+
+impl fmt::Debug for ResetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResetError::IncompleteError { ref failures } => {
+                write!(f, "could not clean up {}", failures.join(", "))
+            }
+            ResetError::UnknownError => write!(f, "ResetError"),
+        }
+    }
+}
+
+impl<'a> Reset<'a> {
+    pub fn run(config: &Config, wipe_pki: bool, wipe_etcd_data: bool) -> Result<(), ResetError> {
+        let reset = Reset {
+            config: config,
+            wipe_pki: wipe_pki,
+            wipe_etcd_data: wipe_etcd_data,
+        };
+        info!("resetting");
+        let mut inventory = Inventory::load(config).map_err(
+            failed("could not read the inventory"),
+        )?;
+        reset.delete_node();
+        let mut failures = reset.stop_services(&mut inventory);
+        failures.extend(reset.remove_files(&mut inventory));
+        if reset
+            .reload_units()
+            .map_err(failed("failed reloading systemd units"))
+            .is_err()
+        {
+            failures.push("systemd units".to_string());
+        }
+        if let Err(etcd_data_dir) = reset.remove_etcd_data(&mut inventory) {
+            failures.push(etcd_data_dir);
+        }
+        reset.save_inventory(&inventory).map_err(
+            failed("failed updating the inventory"),
+        )?;
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ResetError::IncompleteError { failures: failures })
+        }
+    }
+
+    fn delete_node(&self) {
+        info!("deleting node from the cluster");
+        if K8s::delete_node(self.config).is_err() {
+            warn!("could not delete the node object; the cluster is not reachable");
+        }
+    }
+
+    fn stop_service(&self, service: &str) -> Result<&Reset, ResetError> {
+        info!("stopping and disabling {}", service);
+        Systemd::stop(service)?;
+        Systemd::disable(service)?;
+        Ok(self)
+    }
+
+    fn stop_services(&self, inventory: &mut Inventory) -> Vec<String> {
+        let mut failures = Vec::new();
+        for service in inventory.services.iter().rev() {
+            if self.stop_service(service).is_err() {
+                error!("could not stop and disable {}", service);
+                failures.push(service.clone());
+            }
+        }
+        inventory.services.retain(|service| failures.contains(service));
+        failures
+    }
+
+    fn remove_file(&self, inventory: &Inventory, file: &str) -> Result<&Reset, ResetError> {
+        if Path::new(file).exists() {
+            if !inventory.unmodified(file, &fs::read(file)?) {
+                warn!("{} was modified after kubeception wrote it", file);
+            }
+            info!("removing {}", file);
+            fs::remove_file(file)?;
+        }
+        Ok(self)
+    }
+
+    fn remove_files(&self, inventory: &mut Inventory) -> Vec<String> {
+        let mut failures = Vec::new();
+        let mut kinds = vec![
+            FileKind::StaticManifest,
+            FileKind::Configuration,
+            FileKind::Kubeconfig,
+        ];
+        if self.wipe_pki {
+            kinds.push(FileKind::Pki);
+        }
+        for kind in kinds {
+            for file in inventory.paths(kind) {
+                if self.remove_file(inventory, &file).is_err() {
+                    error!("could not remove {}", file);
+                    failures.push(file);
+                } else {
+                    inventory.forget(&file);
+                }
+            }
+        }
+        failures
+    }
+
+    fn reload_units(&self) -> Result<&Reset, ResetError> {
+        if self.config.profile()?.is_drop_in() {
+            Systemd::daemon_reload()?;
+        }
+        Ok(self)
+    }
+
+    fn remove_etcd_data(&self, inventory: &mut Inventory) -> Result<&Reset, String> {
+        if !self.wipe_etcd_data {
+            return Ok(self);
+        }
+        if let Some(etcd_data_dir) = inventory.etcd_data_dir.take() {
+            if Path::new(&etcd_data_dir).is_dir() {
+                info!("removing {}", etcd_data_dir);
+                if fs::remove_dir_all(&etcd_data_dir).is_err() {
+                    error!("could not remove {}", etcd_data_dir);
+                    inventory.etcd_data_dir = Some(etcd_data_dir.clone());
+                    return Err(etcd_data_dir);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    fn save_inventory(&self, inventory: &Inventory) -> Result<&Reset, ResetError> {
+        State::clear(self.config)?;
+        if inventory.is_empty() {
+            let path = Inventory::path(self.config);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        } else {
+            inventory.save(self.config)?;
+        }
+        Ok(self)
+    }
+}
@@ -1,5 +1,7 @@
+pub const ETCD_DATA_DIR: &'static str = "/var/lib/etcd/default.etcd";
+
 pub const ETCD_BOOTSTRAP_CONFIG: &'static str = r#"ETCD_NAME=default
-ETCD_DATA_DIR="/var/lib/etcd/default.etcd"
+ETCD_DATA_DIR="{{etcd_data_dir}}"
 ETCD_LISTEN_CLIENT_URLS="https://0.0.0.0:2379"
 ETCD_ADVERTISE_CLIENT_URLS="https://0.0.0.0:2379"
 ETCD_CLIENT_CERT_AUTH="true"
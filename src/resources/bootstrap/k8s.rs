@@ -13,7 +13,7 @@ metadata:
 spec:
   containers:
   - name: kube-apiserver
-    image: k8s.gcr.io/hyperkube:v1.11.0
+    image: {{hyperkube_image}}
     command:
     - /hyperkube
     - apiserver
@@ -32,11 +32,39 @@ spec:
     - --kubelet-client-key=/etc/kubernetes/secrets/apiserver.key
     - --secure-port=6444
     - --service-account-key-file=/etc/kubernetes/secrets/service-account.pub
-    - --service-cluster-ip-range=10.3.0.0/24
+    - --service-cluster-ip-range={{service_cluster_ip_range}}
     - --cloud-provider=
     - --storage-backend=etcd3
     - --tls-cert-file=/etc/kubernetes/secrets/apiserver.crt
     - --tls-private-key-file=/etc/kubernetes/secrets/apiserver.key
+{{#if audit_enabled}}
+    - --audit-policy-file=/etc/kubernetes/secrets/audit-policy.yaml
+    - --audit-log-path=/var/log/kubernetes/audit/audit.log
+    - --audit-log-maxage={{audit_max_age}}
+    - --audit-log-maxbackup={{audit_max_backup}}
+    - --audit-log-maxsize={{audit_max_size}}
+{{/if}}
+{{#if audit_webhook_config}}
+    - --audit-webhook-config-file=/etc/kubernetes/secrets/audit-webhook.kubeconfig
+    - --audit-webhook-mode={{audit_webhook_mode}}
+{{/if}}
+{{#if encryption_enabled}}
+    - {{encryption_provider_config_flag}}=/etc/kubernetes/secrets/encryption-config.yaml
+{{/if}}
+{{#if oidc_issuer_url}}
+    - --oidc-issuer-url={{{oidc_issuer_url}}}
+    - --oidc-client-id={{{oidc_client_id}}}
+    - --oidc-username-claim={{{oidc_username_claim}}}
+{{#if oidc_groups_claim}}
+    - --oidc-groups-claim={{{oidc_groups_claim}}}
+{{/if}}
+{{#if oidc_ca}}
+    - --oidc-ca-file=/etc/kubernetes/secrets/oidc-ca.crt
+{{/if}}
+{{/if}}
+{{#each apiserver_args}}
+    - {{{this}}}
+{{/each}}
     env:
     - name: POD_IP
       valueFrom:
@@ -49,11 +77,21 @@ spec:
     - mountPath: /etc/kubernetes/secrets
       name: secrets
       readOnly: true
+{{#if audit_enabled}}
+    - mountPath: /var/log/kubernetes/audit
+      name: audit-log
+{{/if}}
   hostNetwork: true
   volumes:
   - name: secrets
     hostPath:
       path: {{bootstrap_secrets_path}}
+{{#if audit_enabled}}
+  - name: audit-log
+    hostPath:
+      path: {{audit_log_dir}}
+      type: DirectoryOrCreate
+{{/if}}
   - name: ssl-certs-host
     hostPath:
       path: {{ca_certificates_path}}
@@ -67,13 +105,13 @@ metadata:
 spec:
   containers:
   - name: kube-controller-manager
-    image: k8s.gcr.io/hyperkube:v1.11.0
+    image: {{hyperkube_image}}
     command:
     - ./hyperkube
     - controller-manager
     - --allocate-node-cidrs=true
-    - --cluster-cidr=10.2.0.0/16
-    - --service-cluster-ip-range=10.3.0.0/24
+    - --cluster-cidr={{cluster_cidr}}
+    - --service-cluster-ip-range={{service_cluster_ip_range}}
     - --cloud-provider=
     - --cluster-signing-cert-file=/etc/kubernetes/secrets/ca.crt
     - --cluster-signing-key-file=/etc/kubernetes/secrets/ca.key
@@ -82,6 +120,9 @@ spec:
     - --leader-elect=true
     - --root-ca-file=/etc/kubernetes/secrets/ca.crt
     - --service-account-private-key-file=/etc/kubernetes/secrets/service-account.key
+{{#each controller_manager_args}}
+    - {{{this}}}
+{{/each}}
     volumeMounts:
     - name: secrets
       mountPath: /etc/kubernetes/secrets
@@ -107,12 +148,15 @@ metadata:
 spec:
   containers:
   - name: kube-scheduler
-    image: k8s.gcr.io/hyperkube:v1.11.0
+    image: {{hyperkube_image}}
     command:
     - ./hyperkube
     - scheduler
     - --kubeconfig=/etc/kubernetes/secrets/kubeconfig-bootstrap
     - --leader-elect=true
+{{#each scheduler_args}}
+    - {{{this}}}
+{{/each}}
     volumeMounts:
     - name: secrets
       mountPath: /etc/kubernetes/secrets
@@ -124,6 +168,10 @@ spec:
       path: {{bootstrap_secrets_path}}
 "#;
 
+pub const IMAGE_PULL_POD: &'static str = "kubeception-image-pull";
+
+pub const KUBELET_PODS_URL: &'static str = "http://127.0.0.1:10255/pods";
+
 pub const KUBECONFIG: &'static str = r#"apiVersion: v1
 kind: Config
 clusters:
@@ -1,3 +1,5 @@
+pub const KUBELET_DOCKER_CONFIG: &'static str = "/var/lib/kubelet/config.json";
+
 pub const KUBELET_CONFIG: &'static str = r#"KUBELET_ADDRESS="--address=127.0.0.1"
-KUBELET_ARGS="--pod-manifest-path=/etc/kubernetes/manifests --volume-plugin-dir=/usr/lib"
+KUBELET_ARGS="--pod-manifest-path=/etc/kubernetes/manifests --volume-plugin-dir=/usr/lib --pod-infra-container-image={{pause_image}}{{kubelet_extra_args}}"
 "#;
@@ -0,0 +1,67 @@
+pub const AUDIT_POLICY: &'static str = r#"
+apiVersion: audit.k8s.io/v1beta1
+kind: Policy
+omitStages:
+- RequestReceived
+rules:
+- level: None
+  users:
+  - system:kube-proxy
+  verbs:
+  - watch
+  resources:
+  - group: ""
+    resources:
+    - endpoints
+    - services
+    - services/status
+- level: None
+  userGroups:
+  - system:nodes
+  verbs:
+  - get
+  resources:
+  - group: ""
+    resources:
+    - nodes
+    - nodes/status
+- level: None
+  users:
+  - system:kube-controller-manager
+  - system:kube-scheduler
+  - system:serviceaccount:kube-system:endpoint-controller
+  verbs:
+  - get
+  - update
+  namespaces:
+  - kube-system
+  resources:
+  - group: ""
+    resources:
+    - endpoints
+- level: None
+  nonResourceURLs:
+  - /healthz*
+  - /version
+  - /swagger*
+- level: None
+  resources:
+  - group: ""
+    resources:
+    - events
+- level: Metadata
+  resources:
+  - group: ""
+    resources:
+    - secrets
+    - configmaps
+  - group: authentication.k8s.io
+    resources:
+    - tokenreviews
+- level: Metadata
+  verbs:
+  - get
+  - list
+  - watch
+- level: Request
+"#;
@@ -0,0 +1,197 @@
+pub const ALL_MANIFESTS: &'static [(&'static str, &'static str)] = &[
+    ("kube-flannel-cfg", FLANNEL_CFG),
+    ("flannel-cluster-role-binding", FLANNEL_CLUSTER_ROLE_BINDING),
+    ("flannel-cluster-role", FLANNEL_CLUSTER_ROLE),
+    ("flannel-service-account", FLANNEL_SA),
+    ("kube-flannel", FLANNEL),
+];
+
+const FLANNEL_CFG: &'static str = r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: kube-flannel-cfg
+  namespace: kube-system
+  labels:
+    tier: node
+    k8s-app: flannel
+data:
+  cni-conf.json: |
+    {
+      "name": "cbr0",
+      "cniVersion": "0.3.1",
+      "plugins": [
+        {
+          "type": "flannel",
+          "delegate": {
+            "hairpinMode": true,
+            "isDefaultGateway": true{{#if flannel_mtu}},
+            "mtu": {{flannel_mtu}}{{/if}}
+          }
+        },
+        {
+          "type": "portmap",
+          "capabilities": {
+            "portMappings": true
+          }
+        }
+      ]
+    }
+  net-conf.json: |
+    {
+      "Network": "{{cluster_cidr}}",
+      "Backend": {
+        "Type": "{{flannel_backend}}"{{#if flannel_vxlan_port}},
+        "Port": {{flannel_vxlan_port}}{{/if}}
+      }
+    }
+"#;
+
+const FLANNEL_CLUSTER_ROLE_BINDING: &'static str = r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: flannel
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: flannel
+subjects:
+- kind: ServiceAccount
+  name: flannel
+  namespace: kube-system
+"#;
+
+const FLANNEL_CLUSTER_ROLE: &'static str = r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: flannel
+rules:
+  - apiGroups:
+      - ""
+    resources:
+      - pods
+    verbs:
+      - get
+  - apiGroups:
+      - ""
+    resources:
+      - nodes
+    verbs:
+      - list
+      - watch
+  - apiGroups:
+      - ""
+    resources:
+      - nodes/status
+    verbs:
+      - patch
+"#;
+
+const FLANNEL_SA: &'static str = r#"
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: flannel
+  namespace: kube-system
+"#;
+
+const FLANNEL: &'static str = r#"
+apiVersion: apps/v1
+kind: DaemonSet
+metadata:
+  name: kube-flannel
+  namespace: kube-system
+  labels:
+    tier: node
+    k8s-app: flannel
+spec:
+  selector:
+    matchLabels:
+      tier: node
+      k8s-app: flannel
+  template:
+    metadata:
+      labels:
+        tier: node
+        k8s-app: flannel
+    spec:
+      serviceAccountName: flannel
+{{#if image_pull_secret}}
+      imagePullSecrets:
+      - name: kubeception-image-pull-secret
+{{/if}}
+      containers:
+      - name: kube-flannel
+        image: {{flannel_image}}
+        command:
+        - /opt/bin/flanneld
+        - --ip-masq
+        - --kube-subnet-mgr
+{{#if flannel_iface}}
+        - --iface={{flannel_iface}}
+{{/if}}
+{{#if flannel_iface_regex}}
+        - '--iface-regex={{flannel_iface_regex}}'
+{{/if}}
+        securityContext:
+          privileged: true
+        env:
+        - name: POD_NAME
+          valueFrom:
+            fieldRef:
+              fieldPath: metadata.name
+        - name: POD_NAMESPACE
+          valueFrom:
+            fieldRef:
+              fieldPath: metadata.namespace
+        - name: POD_IP
+          valueFrom:
+            fieldRef:
+              fieldPath: status.podIP
+        volumeMounts:
+        - name: run
+          mountPath: /run
+        - name: cni
+          mountPath: /etc/cni/net.d
+        - name: flannel-cfg
+          mountPath: /etc/kube-flannel/
+      - name: install-cni
+        image: {{flannel_cni_image}}
+        command: ["/install-cni.sh"]
+        env:
+        - name: CNI_NETWORK_CONFIG
+          valueFrom:
+            configMapKeyRef:
+              name: kube-flannel-cfg
+              key: cni-conf.json
+        volumeMounts:
+        - name: cni
+          mountPath: /host/etc/cni/net.d
+        - name: host-cni-bin
+          mountPath: /host/opt/cni/bin/
+      hostNetwork: true
+      tolerations:
+      - effect: NoSchedule
+        operator: Exists
+      - effect: NoExecute
+        operator: Exists
+      volumes:
+        - name: run
+          hostPath:
+            path: /run
+        - name: cni
+          hostPath:
+            path: /etc/kubernetes/cni/net.d
+        - name: flannel-cfg
+          configMap:
+            name: kube-flannel-cfg
+        - name: host-cni-bin
+          hostPath:
+            path: /opt/cni/bin
+  updateStrategy:
+    rollingUpdate:
+      maxUnavailable: 1
+    type: RollingUpdate
+"#;
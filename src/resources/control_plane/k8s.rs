@@ -1,205 +1,38 @@
-pub const ALL_MANIFESTS: &'static [&'static str] = &[
-    FLANNEL_CFG,
-    FLANNEL_CLUSTER_ROLE_BINDING,
-    FLANNEL_CLUSTER_ROLE,
-    FLANNEL_SA,
-    FLANNEL,
-    KUBE_APISERVER_SECRET,
-    KUBE_APISERVER,
-    KUBECONFIG,
-    KUBECONFIG_IN_CLUSTER,
-    KUBE_CONTROLLER_MANAGER_DISRUPTION,
-    KUBE_CONTROLLER_MANAGER_ROLE_BINDING,
-    KUBE_CONTROLLER_MANAGER_SECRET,
-    KUBE_CONTROLLER_MANAGER_SERVICE_ACCOUNT,
-    KUBE_CONTROLLER_MANAGER,
-    KUBE_DNS_DEPLOYMENT,
-    KUBE_DNS_SVC,
-    KUBE_PROXY_ROLE_BINDING,
-    KUBE_PROXY_SA,
-    KUBE_PROXY,
-    KUBE_SCHEDULER_DISRUPTION,
-    KUBE_SCHEDULER,
-    KUBE_SYSTEM_RBAC_ROLE_BINDING,
+pub const ALL_MANIFESTS: &'static [(&'static str, &'static str)] = &[
+    ("kube-apiserver-secret", KUBE_APISERVER_SECRET),
+    ("kube-apiserver", KUBE_APISERVER),
+    ("kubeconfig", KUBECONFIG),
+    ("kubeconfig-in-cluster", KUBECONFIG_IN_CLUSTER),
+    ("kube-controller-manager-disruption", KUBE_CONTROLLER_MANAGER_DISRUPTION),
+    ("kube-controller-manager-role-binding", KUBE_CONTROLLER_MANAGER_ROLE_BINDING),
+    ("kube-controller-manager-secret", KUBE_CONTROLLER_MANAGER_SECRET),
+    ("kube-controller-manager-service-account", KUBE_CONTROLLER_MANAGER_SERVICE_ACCOUNT),
+    ("kube-controller-manager", KUBE_CONTROLLER_MANAGER),
+    ("kube-proxy-role-binding", KUBE_PROXY_ROLE_BINDING),
+    ("kube-proxy-service-account", KUBE_PROXY_SA),
+    ("kube-proxy", KUBE_PROXY),
+    ("kube-scheduler-disruption", KUBE_SCHEDULER_DISRUPTION),
+    ("kube-scheduler-role-binding", KUBE_SCHEDULER_ROLE_BINDING),
+    ("kube-scheduler-volume-role-binding", KUBE_SCHEDULER_VOLUME_ROLE_BINDING),
+    ("kube-scheduler-service-account", KUBE_SCHEDULER_SERVICE_ACCOUNT),
+    ("kube-scheduler", KUBE_SCHEDULER),
 ];
 
-const FLANNEL_CFG: &'static str = r#"
+pub const STALE_RBAC_RESOURCES: &'static [(&'static str, &'static str)] =
+    &[("clusterrolebinding", "system:default-sa")];
+
+pub const IMAGE_PULL_SECRET: &'static str = r#"
 apiVersion: v1
-kind: ConfigMap
-metadata:
-  name: kube-flannel-cfg
-  namespace: kube-system
-  labels:
-    tier: node
-    k8s-app: flannel
 data:
-  cni-conf.json: |
-    {
-      "name": "cbr0",
-      "cniVersion": "0.3.1",
-      "plugins": [
-        {
-          "type": "flannel",
-          "delegate": {
-            "hairpinMode": true,
-            "isDefaultGateway": true
-          }
-        },
-        {
-          "type": "portmap",
-          "capabilities": {
-            "portMappings": true
-          }
-        }
-      ]
-    }
-  net-conf.json: |
-    {
-      "Network": "{{cluster_cidr}}",
-      "Backend": {
-        "Type": "vxlan",
-        "Port": 4789
-      }
-    }
-"#;
-
-const FLANNEL_CLUSTER_ROLE_BINDING: &'static str = r#"
-apiVersion: rbac.authorization.k8s.io/v1
-kind: ClusterRoleBinding
+  .dockerconfigjson: {{image_pull_secret}}
+kind: Secret
 metadata:
-  name: flannel
-roleRef:
-  apiGroup: rbac.authorization.k8s.io
-  kind: ClusterRole
-  name: flannel
-subjects:
-- kind: ServiceAccount
-  name: flannel
+  name: kubeception-image-pull-secret
   namespace: kube-system
+type: kubernetes.io/dockerconfigjson
 "#;
 
-const FLANNEL_CLUSTER_ROLE: &'static str = r#"
-apiVersion: rbac.authorization.k8s.io/v1
-kind: ClusterRole
-metadata:
-  name: flannel
-rules:
-  - apiGroups:
-      - ""
-    resources:
-      - pods
-    verbs:
-      - get
-  - apiGroups:
-      - ""
-    resources:
-      - nodes
-    verbs:
-      - list
-      - watch
-  - apiGroups:
-      - ""
-    resources:
-      - nodes/status
-    verbs:
-      - patch
-"#;
-
-const FLANNEL_SA: &'static str = r#"
-apiVersion: v1
-kind: ServiceAccount
-metadata:
-  name: flannel
-  namespace: kube-system
-"#;
-
-const FLANNEL: &'static str = r#"
-apiVersion: apps/v1
-kind: DaemonSet
-metadata:
-  name: kube-flannel
-  namespace: kube-system
-  labels:
-    tier: node
-    k8s-app: flannel
-spec:
-  selector:
-    matchLabels:
-      tier: node
-      k8s-app: flannel
-  template:
-    metadata:
-      labels:
-        tier: node
-        k8s-app: flannel
-    spec:
-      serviceAccountName: flannel
-      containers:
-      - name: kube-flannel
-        image: quay.io/coreos/flannel:v0.10.0-amd64
-        command: ["/opt/bin/flanneld", "--ip-masq", "--kube-subnet-mgr", "--iface=$(POD_IP)"]
-        securityContext:
-          privileged: true
-        env:
-        - name: POD_NAME
-          valueFrom:
-            fieldRef:
-              fieldPath: metadata.name
-        - name: POD_NAMESPACE
-          valueFrom:
-            fieldRef:
-              fieldPath: metadata.namespace
-        - name: POD_IP
-          valueFrom:
-            fieldRef:
-              fieldPath: status.podIP
-        volumeMounts:
-        - name: run
-          mountPath: /run
-        - name: cni
-          mountPath: /etc/cni/net.d
-        - name: flannel-cfg
-          mountPath: /etc/kube-flannel/
-      - name: install-cni
-        image: quay.io/coreos/flannel-cni:v0.3.0
-        command: ["/install-cni.sh"]
-        env:
-        - name: CNI_NETWORK_CONFIG
-          valueFrom:
-            configMapKeyRef:
-              name: kube-flannel-cfg
-              key: cni-conf.json
-        volumeMounts:
-        - name: cni
-          mountPath: /host/etc/cni/net.d
-        - name: host-cni-bin
-          mountPath: /host/opt/cni/bin/
-      hostNetwork: true
-      tolerations:
-      - effect: NoSchedule
-        operator: Exists
-      - effect: NoExecute
-        operator: Exists
-      volumes:
-        - name: run
-          hostPath:
-            path: /run
-        - name: cni
-          hostPath:
-            path: /etc/kubernetes/cni/net.d
-        - name: flannel-cfg
-          configMap:
-            name: kube-flannel-cfg
-        - name: host-cni-bin
-          hostPath:
-            path: /opt/cni/bin
-  updateStrategy:
-    rollingUpdate:
-      maxUnavailable: 1
-    type: RollingUpdate
-"#;
-
-const KUBE_APISERVER_SECRET: &'static str = r#"
+pub const KUBE_APISERVER_SECRET: &'static str = r#"
 apiVersion: v1
 data:
   apiserver.crt: {{apiserver_crt}}
@@ -209,6 +42,18 @@ data:
   etcd-client.crt: {{etcd_client_crt}}
   etcd-client.key: {{etcd_client_key}}
   service-account.pub: {{service_account_pub}}
+{{#if audit_enabled}}
+  audit-policy.yaml: {{audit_policy}}
+{{/if}}
+{{#if audit_webhook_config}}
+  audit-webhook.kubeconfig: {{audit_webhook_config}}
+{{/if}}
+{{#if encryption_enabled}}
+  encryption-config.yaml: {{encryption_config}}
+{{/if}}
+{{#if oidc_ca}}
+  oidc-ca.crt: {{oidc_ca}}
+{{/if}}
 kind: Secret
 metadata:
   name: kube-apiserver
@@ -216,7 +61,7 @@ metadata:
 type: Opaque
 "#;
 
-const KUBE_APISERVER: &'static str = r#"
+pub const KUBE_APISERVER: &'static str = r#"
 apiVersion: apps/v1
 kind: DaemonSet
 metadata:
@@ -235,10 +80,18 @@ spec:
       labels:
         tier: control-plane
         k8s-app: kube-apiserver
+{{#if encryption_config_checksum}}
+      annotations:
+        checksum/encryption-config: {{encryption_config_checksum}}
+{{/if}}
     spec:
+{{#if image_pull_secret}}
+      imagePullSecrets:
+      - name: kubeception-image-pull-secret
+{{/if}}
       containers:
       - name: kube-apiserver
-        image: k8s.gcr.io/hyperkube:v1.11.0
+        image: {{hyperkube_image}}
         command:
         - /hyperkube
         - apiserver
@@ -263,6 +116,34 @@ spec:
         - --storage-backend=etcd3
         - --tls-cert-file=/etc/kubernetes/secrets/apiserver.crt
         - --tls-private-key-file=/etc/kubernetes/secrets/apiserver.key
+{{#if audit_enabled}}
+        - --audit-policy-file=/etc/kubernetes/secrets/audit-policy.yaml
+        - --audit-log-path=/var/log/kubernetes/audit/audit.log
+        - --audit-log-maxage={{audit_max_age}}
+        - --audit-log-maxbackup={{audit_max_backup}}
+        - --audit-log-maxsize={{audit_max_size}}
+{{/if}}
+{{#if audit_webhook_config}}
+        - --audit-webhook-config-file=/etc/kubernetes/secrets/audit-webhook.kubeconfig
+        - --audit-webhook-mode={{audit_webhook_mode}}
+{{/if}}
+{{#if encryption_enabled}}
+        - {{encryption_provider_config_flag}}=/etc/kubernetes/secrets/encryption-config.yaml
+{{/if}}
+{{#if oidc_issuer_url}}
+        - --oidc-issuer-url={{{oidc_issuer_url}}}
+        - --oidc-client-id={{{oidc_client_id}}}
+        - --oidc-username-claim={{{oidc_username_claim}}}
+{{#if oidc_groups_claim}}
+        - --oidc-groups-claim={{{oidc_groups_claim}}}
+{{/if}}
+{{#if oidc_ca}}
+        - --oidc-ca-file=/etc/kubernetes/secrets/oidc-ca.crt
+{{/if}}
+{{/if}}
+{{#each apiserver_args}}
+        - {{{this}}}
+{{/each}}
         env:
         - name: POD_IP
           valueFrom:
@@ -275,6 +156,10 @@ spec:
         - mountPath: /etc/kubernetes/secrets
           name: secrets
           readOnly: true
+{{#if audit_enabled}}
+        - mountPath: /var/log/kubernetes/audit
+          name: audit-log
+{{/if}}
       hostNetwork: true
       nodeSelector:
         node-role.kubernetes.io/master: ""
@@ -285,10 +170,16 @@ spec:
       volumes:
       - name: ssl-certs-host
         hostPath:
-          path: /etc/ca-certificates
+          path: {{ca_certificates_path}}
       - name: secrets
         secret:
           secretName: kube-apiserver
+{{#if audit_enabled}}
+      - name: audit-log
+        hostPath:
+          path: {{audit_log_dir}}
+          type: DirectoryOrCreate
+{{/if}}
       securityContext:
         runAsNonRoot: true
         runAsUser: 65534
@@ -435,9 +326,13 @@ spec:
                   values:
                   - kube-controller-manager
               topologyKey: kubernetes.io/hostname
+{{#if image_pull_secret}}
+      imagePullSecrets:
+      - name: kubeception-image-pull-secret
+{{/if}}
       containers:
       - name: kube-controller-manager
-        image: k8s.gcr.io/hyperkube:v1.11.0
+        image: {{hyperkube_image}}
         command:
         - ./hyperkube
         - controller-manager
@@ -452,6 +347,9 @@ spec:
         - --leader-elect=true
         - --root-ca-file=/etc/kubernetes/secrets/ca.crt
         - --service-account-private-key-file=/etc/kubernetes/secrets/service-account.key
+{{#each controller_manager_args}}
+        - {{{this}}}
+{{/each}}
         livenessProbe:
           httpGet:
             path: /healthz
@@ -481,189 +379,10 @@ spec:
           secretName: kube-controller-manager
       - name: ssl-host
         hostPath:
-          path: /etc/ca-certificates
+          path: {{ca_certificates_path}}
       dnsPolicy: ClusterFirstWithHostNet
 "#;
 
-const KUBE_DNS_DEPLOYMENT: &'static str = r#"
-apiVersion: apps/v1
-kind: Deployment
-metadata:
-  name: kube-dns
-  namespace: kube-system
-  labels:
-    k8s-app: kube-dns
-    kubernetes.io/cluster-service: "true"
-    addonmanager.kubernetes.io/mode: Reconcile
-spec:
-  # replicas: not specified here:
-  # 1. In order to make Addon Manager do not reconcile this replicas parameter.
-  # 2. Default is 1.
-  # 3. Will be tuned in real time if DNS horizontal auto-scaling is turned on.
-  strategy:
-    rollingUpdate:
-      maxSurge: 10%
-      maxUnavailable: 0
-  selector:
-    matchLabels:
-      k8s-app: kube-dns
-  template:
-    metadata:
-      labels:
-        k8s-app: kube-dns
-    spec:
-      nodeSelector:
-        node-role.kubernetes.io/master: ""
-      tolerations:
-      - key: node-role.kubernetes.io/master
-        operator: Exists
-        effect: NoSchedule
-      volumes:
-      - name: kube-dns-config
-        configMap:
-          name: kube-dns
-          optional: true
-      containers:
-      - name: kubedns
-        image: k8s.gcr.io/k8s-dns-kube-dns-amd64:1.14.10
-        resources:
-          # TODO: Set memory limits when we've profiled the container for large
-          # clusters, then set request = limit to keep this container in
-          # guaranteed class. Currently, this container falls into the
-          # "burstable" category so the kubelet doesn't backoff from restarting it.
-          limits:
-            memory: 170Mi
-          requests:
-            cpu: 100m
-            memory: 70Mi
-        livenessProbe:
-          httpGet:
-            path: /healthcheck/kubedns
-            port: 10054
-            scheme: HTTP
-          initialDelaySeconds: 60
-          timeoutSeconds: 5
-          successThreshold: 1
-          failureThreshold: 5
-        readinessProbe:
-          httpGet:
-            path: /readiness
-            port: 8081
-            scheme: HTTP
-          # we poll on pod startup for the Kubernetes master service and
-          # only setup the /readiness HTTP server once that's available.
-          initialDelaySeconds: 3
-          timeoutSeconds: 5
-        args:
-        - --domain=cluster.local.
-        - --dns-port=10053
-        - --config-dir=/kube-dns-config
-        - --v=2
-        env:
-        - name: PROMETHEUS_PORT
-          value: "10055"
-        ports:
-        - containerPort: 10053
-          name: dns-local
-          protocol: UDP
-        - containerPort: 10053
-          name: dns-tcp-local
-          protocol: TCP
-        - containerPort: 10055
-          name: metrics
-          protocol: TCP
-        volumeMounts:
-        - name: kube-dns-config
-          mountPath: /kube-dns-config
-      - name: dnsmasq
-        image: k8s.gcr.io/k8s-dns-dnsmasq-nanny-amd64:1.14.10
-        livenessProbe:
-          httpGet:
-            path: /healthcheck/dnsmasq
-            port: 10054
-            scheme: HTTP
-          initialDelaySeconds: 60
-          timeoutSeconds: 5
-          successThreshold: 1
-          failureThreshold: 5
-        args:
-        - -v=2
-        - -logtostderr
-        - -configDir=/etc/k8s/dns/dnsmasq-nanny
-        - -restartDnsmasq=true
-        - --
-        - -k
-        - --cache-size=1000
-        - --no-negcache
-        - --log-facility=-
-        - --server=/cluster.local/127.0.0.1#10053
-        - --server=/in-addr.arpa/127.0.0.1#10053
-        - --server=/ip6.arpa/127.0.0.1#10053
-        ports:
-        - containerPort: 53
-          name: dns
-          protocol: UDP
-        - containerPort: 53
-          name: dns-tcp
-          protocol: TCP
-        # see: https://github.com/kubernetes/kubernetes/issues/29055 for details
-        resources:
-          requests:
-            cpu: 150m
-            memory: 20Mi
-        volumeMounts:
-        - name: kube-dns-config
-          mountPath: /etc/k8s/dns/dnsmasq-nanny
-      - name: sidecar
-        image: k8s.gcr.io/k8s-dns-sidecar-amd64:1.14.10
-        livenessProbe:
-          httpGet:
-            path: /metrics
-            port: 10054
-            scheme: HTTP
-          initialDelaySeconds: 60
-          timeoutSeconds: 5
-          successThreshold: 1
-          failureThreshold: 5
-        args:
-        - --v=2
-        - --logtostderr
-        - --probe=kubedns,127.0.0.1:10053,kubernetes.default.svc.cluster.local,5,SRV
-        - --probe=dnsmasq,127.0.0.1:53,kubernetes.default.svc.cluster.local,5,SRV
-        ports:
-        - containerPort: 10054
-          name: metrics
-          protocol: TCP
-        resources:
-          requests:
-            memory: 20Mi
-            cpu: 10m
-      dnsPolicy: Default  # Don't use cluster DNS.
-"#;
-
-const KUBE_DNS_SVC: &'static str = r#"
-apiVersion: v1
-kind: Service
-metadata:
-  name: kube-dns
-  namespace: kube-system
-  labels:
-    k8s-app: kube-dns
-    kubernetes.io/cluster-service: "true"
-    kubernetes.io/name: "KubeDNS"
-spec:
-  selector:
-    k8s-app: kube-dns
-  clusterIP: {{dns_cluster_ip}}
-  ports:
-  - name: dns
-    port: 53
-    protocol: UDP
-  - name: dns-tcp
-    port: 53
-    protocol: TCP
-"#;
-
 const KUBE_PROXY_ROLE_BINDING: &'static str = r#"
 apiVersion: rbac.authorization.k8s.io/v1
 kind: ClusterRoleBinding
@@ -707,9 +426,13 @@ spec:
         tier: node
         k8s-app: kube-proxy
     spec:
+{{#if image_pull_secret}}
+      imagePullSecrets:
+      - name: kubeception-image-pull-secret
+{{/if}}
       containers:
       - name: kube-proxy
-        image: k8s.gcr.io/hyperkube:v1.11.0
+        image: {{hyperkube_image}}
         command:
         - ./hyperkube
         - proxy
@@ -717,6 +440,9 @@ spec:
         - --hostname-override=$(NODE_NAME)
         - --kubeconfig=/etc/kubernetes/kubeconfig
         - --proxy-mode=iptables
+{{#each proxy_args}}
+        - {{{this}}}
+{{/each}}
         env:
           - name: NODE_NAME
             valueFrom:
@@ -747,7 +473,7 @@ spec:
           path: /lib/modules
       - name: ssl-certs-host
         hostPath:
-          path: /etc/ca-certificates
+          path: {{ca_certificates_path}}
       - name: kubeconfig
         configMap:
           name: kubeconfig-in-cluster
@@ -771,6 +497,44 @@ spec:
       k8s-app: kube-scheduler
 "#;
 
+const KUBE_SCHEDULER_ROLE_BINDING: &'static str = r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: kube-scheduler
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: system:kube-scheduler
+subjects:
+- kind: ServiceAccount
+  name: kube-scheduler
+  namespace: kube-system
+"#;
+
+const KUBE_SCHEDULER_VOLUME_ROLE_BINDING: &'static str = r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: kube-scheduler-volume
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: system:volume-scheduler
+subjects:
+- kind: ServiceAccount
+  name: kube-scheduler
+  namespace: kube-system
+"#;
+
+const KUBE_SCHEDULER_SERVICE_ACCOUNT: &'static str = r#"
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  namespace: kube-system
+  name: kube-scheduler
+"#;
+
 const KUBE_SCHEDULER: &'static str = r#"
 apiVersion: apps/v1
 kind: Deployment
@@ -808,13 +572,20 @@ spec:
                   values:
                   - kube-scheduler
               topologyKey: kubernetes.io/hostname
+{{#if image_pull_secret}}
+      imagePullSecrets:
+      - name: kubeception-image-pull-secret
+{{/if}}
       containers:
       - name: kube-scheduler
-        image: k8s.gcr.io/hyperkube:v1.11.0
+        image: {{hyperkube_image}}
         command:
         - ./hyperkube
         - scheduler
         - --leader-elect=true
+{{#each scheduler_args}}
+        - {{{this}}}
+{{/each}}
         livenessProbe:
           httpGet:
             path: /healthz
@@ -826,23 +597,9 @@ spec:
       securityContext:
         runAsNonRoot: true
         runAsUser: 65534
+      serviceAccountName: kube-scheduler
       tolerations:
       - key: node-role.kubernetes.io/master
         operator: Exists
         effect: NoSchedule
 "#;
-
-const KUBE_SYSTEM_RBAC_ROLE_BINDING: &'static str = r#"
-apiVersion: rbac.authorization.k8s.io/v1
-kind: ClusterRoleBinding
-metadata:
-  name: system:default-sa
-subjects:
-  - kind: ServiceAccount
-    name: default
-    namespace: kube-system
-roleRef:
-  kind: ClusterRole
-  name: cluster-admin
-  apiGroup: rbac.authorization.k8s.io
-"#;
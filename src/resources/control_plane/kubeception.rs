@@ -1,4 +1,8 @@
-pub const ALL_MANIFESTS: &'static [&'static str] = &[KUBECEPTION_SA, KUBECEPTION, KUBECEPTION_SVC];
+pub const ALL_MANIFESTS: &'static [(&'static str, &'static str)] = &[
+    ("kubeception-service-account", KUBECEPTION_SA),
+    ("kubeception", KUBECEPTION),
+    ("kubeception-service", KUBECEPTION_SVC),
+];
 
 const KUBECEPTION_SA: &'static str = r#"
 apiVersion: v1
@@ -6,6 +10,7 @@ kind: ServiceAccount
 metadata:
   namespace: kube-system
   name: kubeception
+automountServiceAccountToken: false # kubeception serve does not call the apiserver.
 "#;
 
 const KUBECEPTION: &'static str = r#"
@@ -45,6 +50,10 @@ spec:
                   values:
                   - kubeception
               topologyKey: kubernetes.io/hostname
+{{#if image_pull_secret}}
+      imagePullSecrets:
+      - name: kubeception-image-pull-secret
+{{/if}}
       containers:
       - name: kubeception
         image: {{kubeception_image}}
@@ -1,3 +1,9 @@
+pub mod audit;
+pub mod calico;
+pub mod canal;
+pub mod dns;
+pub mod flannel;
 pub mod k8s;
 pub mod kubelet;
 pub mod kubeception;
+pub mod oidc;
@@ -0,0 +1,25 @@
+pub const OIDC_KUBECONFIG: &'static str = r#"apiVersion: v1
+kind: Config
+clusters:
+- name: kubeception
+  cluster:
+    server: {{{server}}}
+    certificate-authority-data: {{ca_crt}}
+users:
+- name: {{{oidc_user}}}
+  user:
+    auth-provider:
+      name: oidc
+      config:
+        client-id: {{{oidc_client_id}}}
+        idp-issuer-url: {{{oidc_issuer_url}}}
+{{#if oidc_ca}}
+        idp-certificate-authority-data: {{oidc_ca}}
+{{/if}}
+contexts:
+- name: kubeception
+  context:
+    cluster: kubeception
+    user: {{{oidc_user}}}
+current-context: kubeception
+"#;
@@ -0,0 +1,221 @@
+use serde_json::Value;
+
+fn has_wildcard(values: &Value) -> bool {
+    match values.as_array() {
+        Some(values) => values.iter().any(|value| value.as_str() == Some("*")),
+        None => false,
+    }
+}
+
+fn grants_everything(role: &Value) -> bool {
+    match role["rules"].as_array() {
+        Some(rules) => {
+            rules.iter().any(|rule| {
+                has_wildcard(&rule["apiGroups"]) && has_wildcard(&rule["resources"]) &&
+                    has_wildcard(&rule["verbs"])
+            })
+        }
+        None => false,
+    }
+}
+
+fn find_role<'a>(roles: &'a Value, binding: &Value) -> Option<&'a Value> {
+    let role_ref = &binding["roleRef"];
+    let cluster_role = role_ref["kind"].as_str() == Some("ClusterRole");
+    roles["items"].as_array()?.iter().find(|role| {
+        role["kind"] == role_ref["kind"] && role["metadata"]["name"] == role_ref["name"] &&
+            (cluster_role || role["metadata"]["namespace"] == binding["metadata"]["namespace"])
+    })
+}
+
+fn binding_name(binding: &Value) -> String {
+    let kind = binding["kind"].as_str().unwrap_or("");
+    let name = binding["metadata"]["name"].as_str().unwrap_or("");
+    match binding["metadata"]["namespace"].as_str() {
+        Some(namespace) => format!("{} {}/{}", kind, namespace, name),
+        None => format!("{} {}", kind, name),
+    }
+}
+
+fn subject_name(subject: &Value) -> String {
+    let kind = subject["kind"].as_str().unwrap_or("");
+    let name = subject["name"].as_str().unwrap_or("");
+    match subject["namespace"].as_str() {
+        Some(namespace) => format!("{} {}/{}", kind, namespace, name),
+        None => format!("{} {}", kind, name),
+    }
+}
+
+fn is_builtin(binding: &Value) -> bool {
+    binding["metadata"]["labels"]["kubernetes.io/bootstrapping"].as_str() == Some("rbac-defaults")
+}
+
+pub fn over_privileged_bindings(roles: &Value, bindings: &Value) -> Vec<String> {
+    let mut findings = Vec::new();
+    let bindings = match bindings["items"].as_array() {
+        Some(bindings) => bindings,
+        None => return findings,
+    };
+    for binding in bindings {
+        let subjects = match binding["subjects"].as_array() {
+            Some(subjects) => subjects,
+            None => continue,
+        };
+        let role = format!(
+            "{} {}",
+            binding["roleRef"]["kind"].as_str().unwrap_or(""),
+            binding["roleRef"]["name"].as_str().unwrap_or("")
+        );
+        let full_access = find_role(roles, binding).map_or(false, grants_everything);
+        for subject in subjects {
+            let kind = subject["kind"].as_str().unwrap_or("");
+            let name = subject["name"].as_str().unwrap_or("");
+            if full_access && !(kind == "Group" && name == "system:masters") {
+                findings.push(format!(
+                    "{}: {} grants full access to {}",
+                    binding_name(binding),
+                    role,
+                    subject_name(subject)
+                ));
+            } else if kind == "ServiceAccount" && name == "default" {
+                findings.push(format!(
+                    "{}: {} is granted to {}, which every pod in its namespace runs as by default",
+                    binding_name(binding),
+                    role,
+                    subject_name(subject)
+                ));
+            } else if ((kind == "Group" && name == "system:unauthenticated") ||
+                           (kind == "User" && name == "system:anonymous")) &&
+                       !is_builtin(binding)
+            {
+                findings.push(format!(
+                    "{}: {} is granted to unauthenticated requests",
+                    binding_name(binding),
+                    role
+                ));
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roles() -> Value {
+        json!({
+            "items": [
+                {
+                    "kind": "ClusterRole",
+                    "metadata": {"name": "cluster-admin"},
+                    "rules": [{"apiGroups": ["*"], "resources": ["*"], "verbs": ["*"]}]
+                },
+                {
+                    "kind": "ClusterRole",
+                    "metadata": {"name": "view"},
+                    "rules": [{"apiGroups": [""], "resources": ["pods"], "verbs": ["get", "list"]}]
+                },
+                {
+                    "kind": "Role",
+                    "metadata": {"name": "everything", "namespace": "team"},
+                    "rules": [{"apiGroups": ["*"], "resources": ["*"], "verbs": ["*"]}]
+                }
+            ]
+        })
+    }
+
+    fn binding(kind: &str, namespace: Option<&str>, role: (&str, &str), subject: Value) -> Value {
+        let mut binding = json!({
+            "kind": kind,
+            "metadata": {"name": "binding"},
+            "roleRef": {"kind": role.0, "name": role.1},
+            "subjects": [subject]
+        });
+        if let Some(namespace) = namespace {
+            binding["metadata"]["namespace"] = json!(namespace);
+        }
+        binding
+    }
+
+    fn findings(binding: Value) -> Vec<String> {
+        over_privileged_bindings(&roles(), &json!({ "items": [binding] }))
+    }
+
+    #[test]
+    fn full_access_outside_system_masters() {
+        assert_eq!(
+            findings(binding(
+                "ClusterRoleBinding",
+                None,
+                ("ClusterRole", "cluster-admin"),
+                json!({"kind": "User", "name": "alice"}),
+            )),
+            vec!["ClusterRoleBinding binding: ClusterRole cluster-admin grants full access to User alice"]
+        );
+        assert!(
+            findings(binding(
+                "ClusterRoleBinding",
+                None,
+                ("ClusterRole", "cluster-admin"),
+                json!({"kind": "Group", "name": "system:masters"}),
+            )).is_empty()
+        );
+    }
+
+    #[test]
+    fn roles_are_looked_up_in_the_binding_namespace() {
+        let subject = json!({"kind": "User", "name": "bob"});
+        assert_eq!(
+            findings(binding(
+                "RoleBinding",
+                Some("team"),
+                ("Role", "everything"),
+                subject.clone(),
+            )).len(),
+            1
+        );
+        assert!(
+            findings(binding("RoleBinding", Some("other"), ("Role", "everything"), subject))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn default_service_accounts() {
+        assert_eq!(
+            findings(binding(
+                "RoleBinding",
+                Some("team"),
+                ("ClusterRole", "view"),
+                json!({"kind": "ServiceAccount", "name": "default", "namespace": "team"}),
+            )),
+            vec![
+                "RoleBinding team/binding: ClusterRole view is granted to ServiceAccount team/default, \
+                 which every pod in its namespace runs as by default",
+            ]
+        );
+    }
+
+    #[test]
+    fn unauthenticated_requests() {
+        let anonymous = binding(
+            "ClusterRoleBinding",
+            None,
+            ("ClusterRole", "view"),
+            json!({"kind": "User", "name": "system:anonymous"}),
+        );
+        assert_eq!(findings(anonymous.clone()).len(), 1);
+        let mut builtin = anonymous;
+        builtin["metadata"]["labels"] = json!({"kubernetes.io/bootstrapping": "rbac-defaults"});
+        assert!(findings(builtin).is_empty());
+        assert!(
+            findings(binding(
+                "ClusterRoleBinding",
+                None,
+                ("ClusterRole", "view"),
+                json!({"kind": "Group", "name": "system:authenticated"}),
+            )).is_empty()
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json;
+
+use config::Config;
+use inventory;
+
+#[derive(Serialize, Deserialize)]
+pub struct CompletedPhase {
+    pub name: String,
+    pub inputs: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    #[serde(default)]
+    pub completed: Vec<CompletedPhase>,
+}
+
+pub enum StateError {
+    UnknownError,
+}
+
+impl From<std::io::Error> for StateError {
+    fn from(_error: std::io::Error) -> StateError {
+        StateError::UnknownError
+    }
+}
+
+impl From<serde_json::Error> for StateError {
+    fn from(_error: serde_json::Error) -> StateError {
+        StateError::UnknownError
+    }
+}
+
+impl fmt::Debug for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StateError")
+    }
+}
+
+impl State {
+    fn path(config: &Config) -> PathBuf {
+        config.host_path(PathBuf::from(&config.secrets.path).join(
+            "kubeception-state.json",
+        ))
+    }
+
+    pub fn inputs(config: &Config) -> String {
+        inventory::sha256(
+            format!("{}:{}", env!("CARGO_PKG_VERSION"), config.digest).as_bytes(),
+        )
+    }
+
+    pub fn load(config: &Config) -> Result<State, StateError> {
+        let path = State::path(config);
+        if !path.exists() {
+            return Ok(State::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, config: &Config) -> Result<(), StateError> {
+        fs::write(State::path(config), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn clear(config: &Config) -> Result<(), StateError> {
+        let path = State::path(config);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn completed(&self, name: &str, inputs: &str) -> bool {
+        self.completed.iter().any(|phase| {
+            phase.name == name && phase.inputs == inputs
+        })
+    }
+
+    pub fn complete(&mut self, name: &str, inputs: &str) {
+        self.completed.retain(|phase| phase.name != name);
+        self.completed.push(CompletedPhase {
+            name: name.to_string(),
+            inputs: inputs.to_string(),
+        });
+    }
+}
@@ -2,6 +2,8 @@ use std;
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::Path;
+use std::process::Command;
 
 pub struct System {}
 
@@ -28,4 +30,16 @@ impl System {
         file.read_to_string(&mut contents)?;
         Ok(contents.trim().to_string())
     }
+
+    pub fn chown(path: &Path, uid: u32, gid: u32) -> Result<(), SystemError> {
+        let status = Command::new("chown")
+            .arg(format!("{}:{}", uid, gid))
+            .arg(path)
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(SystemError::UnknownError)
+        }
+    }
 }
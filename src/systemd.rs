@@ -60,4 +60,42 @@ impl Systemd {
         connection.send_with_reply_and_block(message, 2000)?;
         Ok(())
     }
+
+    pub fn daemon_reload() -> Result<(), SystemdError> {
+        let connection = Connection::get_private(BusType::System)?;
+        let message = Message::new_method_call(
+            "org.freedesktop.systemd1",
+            "/org/freedesktop/systemd1",
+            "org.freedesktop.systemd1.Manager",
+            "Reload",
+        )?;
+        connection.send_with_reply_and_block(message, 2000)?;
+        Ok(())
+    }
+
+    pub fn stop<T: Into<String>>(service: T) -> Result<(), SystemdError> {
+        let connection = Connection::get_private(BusType::System)?;
+        let message = Message::new_method_call(
+            "org.freedesktop.systemd1",
+            "/org/freedesktop/systemd1",
+            "org.freedesktop.systemd1.Manager",
+            "StopUnit",
+        )?
+            .append2(service.into(), "replace");
+        connection.send_with_reply_and_block(message, 2000)?;
+        Ok(())
+    }
+
+    pub fn disable<T: Into<String>>(service: T) -> Result<(), SystemdError> {
+        let connection = Connection::get_private(BusType::System)?;
+        let message = Message::new_method_call(
+            "org.freedesktop.systemd1",
+            "/org/freedesktop/systemd1",
+            "org.freedesktop.systemd1.Manager",
+            "DisableUnitFiles",
+        )?
+            .append2(vec![service.into()], false);
+        connection.send_with_reply_and_block(message, 2000)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,8 @@
+use handlebars::{Handlebars, TemplateRenderError};
+use serde_json::Value;
+
+pub fn render(template: &str, context: &Value) -> Result<String, TemplateRenderError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.render_template(template, context)
+}
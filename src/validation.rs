@@ -0,0 +1,681 @@
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+
+use serde_json;
+use serde_yaml;
+
+use config::{Config, FlannelBackend, PatchType};
+use images::COMPONENTS;
+use manifests;
+use net::Cidr;
+
+pub struct ConfigIssue {
+    pub field: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}", line, self.field, self.message),
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
+
+struct Validation<'a> {
+    source: &'a str,
+    issues: Vec<ConfigIssue>,
+}
+
+fn line_of(source: &str, field: &str) -> Option<usize> {
+    let mut parts: Vec<&str> = field.split('.').collect();
+    let key = parts.pop()?;
+    let section = parts.join(".");
+    let mut current_section = String::new();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            current_section = line.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            continue;
+        }
+        if current_section != section || !line.starts_with(key) {
+            continue;
+        }
+        if line[key.len()..].trim_left().starts_with('=') {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+fn valid_image_reference(image: &str) -> bool {
+    let (name, digest) = match image.find('@') {
+        Some(index) => (&image[..index], Some(&image[index + 1..])),
+        None => (image, None),
+    };
+    let last_component = name.rfind('/').map(|index| index + 1).unwrap_or(0);
+    let (repository, tag) = match name[last_component..].find(':') {
+        Some(index) => (
+            &name[..last_component + index],
+            Some(&name[last_component + index + 1..]),
+        ),
+        None => (name, None),
+    };
+    if repository.is_empty() || repository.ends_with('/') || repository.contains("//") {
+        return false;
+    }
+    let mut components: Vec<&str> = repository.split('/').collect();
+    if components.len() > 1 &&
+        (components[0].contains('.') || components[0].contains(':') ||
+             components[0] == "localhost")
+    {
+        components.remove(0);
+    }
+    let valid_components = components.iter().all(|component| {
+        component.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_' || c == '-'
+        }) && component.chars().next().map(|c| c.is_ascii_alphanumeric()) == Some(true)
+    });
+    let valid_tag = match tag {
+        Some(tag) => {
+            !tag.is_empty() && tag.len() <= 128 &&
+                tag.chars().all(|c| {
+                    c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'
+                }) && !tag.starts_with('.') && !tag.starts_with('-')
+        }
+        None => true,
+    };
+    let valid_digest = match digest {
+        Some(digest) => {
+            let mut parts = digest.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(algorithm), Some(hex)) => {
+                    !algorithm.is_empty() && hex.len() >= 32 &&
+                        hex.chars().all(|c| c.is_ascii_hexdigit())
+                }
+                _ => false,
+            }
+        }
+        None => true,
+    };
+    valid_components && valid_tag && valid_digest
+}
+
+fn valid_kubernetes_version(version: &str) -> bool {
+    if !version.starts_with('v') {
+        return false;
+    }
+    let release = version[1..].splitn(2, '-').next().unwrap_or("");
+    let parts: Vec<&str> = release.split('.').collect();
+    parts.len() == 3 &&
+        parts.iter().all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+fn flannel_version(tag: &str) -> Option<(u32, u32, u32)> {
+    let release = tag.trim_left_matches('v').splitn(2, '-').next().unwrap_or("");
+    let parts: Vec<u32> = release
+        .split('.')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    match parts.as_slice() {
+        &[major, minor, patch] => Some((major, minor, patch)),
+        _ => None,
+    }
+}
+
+fn audit_policy_issue(contents: &str) -> Option<String> {
+    let policy = match serde_yaml::from_str::<serde_json::Value>(contents) {
+        Ok(policy) => policy,
+        Err(error) => return Some(format!("is not valid YAML: {}", error)),
+    };
+    let api_version = policy["apiVersion"].as_str().unwrap_or("");
+    if policy["kind"].as_str() != Some("Policy") || !api_version.starts_with("audit.k8s.io/") {
+        return Some("is not an audit.k8s.io Policy document".to_string());
+    }
+    if !policy["rules"].is_array() {
+        return Some("is an audit policy without rules".to_string());
+    }
+    None
+}
+
+fn valid_domain(domain: &str) -> bool {
+    !domain.is_empty() && domain.len() <= 253 &&
+        domain.split('.').all(|label| {
+            !label.is_empty() && label.len() <= 63 && !label.starts_with('-') &&
+                !label.ends_with('-') &&
+                label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+fn valid_digest(digest: &str) -> bool {
+    digest.starts_with("sha256:") && digest.len() == 71 &&
+        digest[7..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl<'a> Validation<'a> {
+    fn issue<T: Into<String>>(&mut self, field: &str, message: T) {
+        self.issues.push(ConfigIssue {
+            field: field.to_string(),
+            line: line_of(self.source, field),
+            message: message.into(),
+        });
+    }
+
+    fn cidr(&mut self, field: &str, value: &str) -> Option<Cidr> {
+        let cidr = Cidr::parse(value);
+        if cidr.is_none() {
+            self.issue(field, format!("{} is not a valid CIDR", value));
+        }
+        cidr
+    }
+
+    fn ip(&mut self, field: &str, value: &str) -> Option<IpAddr> {
+        let ip = value.parse().ok();
+        if ip.is_none() {
+            self.issue(field, format!("{} is not a valid IP address", value));
+        }
+        ip
+    }
+
+    fn nameserver(&mut self, field: &str, value: &str) {
+        if value.parse::<IpAddr>().is_err() && value.parse::<SocketAddr>().is_err() {
+            self.issue(
+                field,
+                format!("{} is not a valid nameserver, such as 8.8.8.8 or 10.0.0.2:5353", value),
+            );
+        }
+    }
+
+    fn absolute_path(&mut self, field: &str, value: &str) {
+        if !Path::new(value).is_absolute() {
+            self.issue(field, format!("{} is not an absolute path", value));
+        }
+    }
+
+    fn image(&mut self, field: &str, value: &str) {
+        if !valid_image_reference(value) {
+            self.issue(field, format!("{} is not a valid image reference", value));
+        }
+    }
+}
+
+fn flannel_tag(config: &Config) -> String {
+    match config.images.flannel.as_ref().and_then(|image| image.tag.clone()) {
+        Some(tag) => tag,
+        None => {
+            COMPONENTS
+                .iter()
+                .find(|component| component.name == "flannel")
+                .and_then(|component| component.tag)
+                .unwrap_or("")
+                .to_string()
+        }
+    }
+}
+
+pub fn warnings(config: &Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if config.net.flannel.backend == FlannelBackend::Wireguard {
+        if let Some(ref flannel) = config.images.flannel {
+            if flannel.tag.is_none() && flannel.digest.is_some() {
+                warnings.push(
+                    "the wireguard backend requires flannel v0.15.0 or later, which cannot be checked for [images.flannel] digest; set tag too"
+                        .to_string(),
+                );
+            }
+        }
+    }
+    warnings
+}
+
+pub fn validate(config: &Config) -> Vec<ConfigIssue> {
+    let mut validation = Validation {
+        source: &config.source,
+        issues: Vec::new(),
+    };
+
+    let cluster_cidr = validation.cidr("net.cluster_cidr", &config.net.cluster_cidr);
+    let service_cluster_ip_range = validation.cidr(
+        "net.service_cluster_ip_range",
+        &config.net.service_cluster_ip_range,
+    );
+    if let (&Some(ref cluster_cidr), &Some(ref service_cluster_ip_range)) =
+        (&cluster_cidr, &service_cluster_ip_range)
+    {
+        if cluster_cidr.overlaps(service_cluster_ip_range) {
+            validation.issue(
+                "net.service_cluster_ip_range",
+                format!(
+                    "{} overlaps with net.cluster_cidr {}",
+                    config.net.service_cluster_ip_range,
+                    config.net.cluster_cidr
+                ),
+            );
+        }
+    }
+    for &(field, value) in &[
+        ("net.apiserver_cluster_ip", &config.net.apiserver_cluster_ip),
+        ("net.dns_cluster_ip", &config.net.dns_cluster_ip),
+    ]
+    {
+        if let Some(ip) = validation.ip(field, value) {
+            if let Some(ref service_cluster_ip_range) = service_cluster_ip_range {
+                if !service_cluster_ip_range.contains(&ip) {
+                    validation.issue(
+                        field,
+                        format!(
+                            "{} is not inside net.service_cluster_ip_range {}",
+                            value,
+                            config.net.service_cluster_ip_range
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    match config.kubeception.nodeport.parse::<u16>() {
+        Ok(30000..=32767) => {}
+        _ => {
+            validation.issue(
+                "kubeception.nodeport",
+                format!(
+                    "{} is not within the NodePort range 30000-32767",
+                    config.kubeception.nodeport
+                ),
+            )
+        }
+    }
+
+    for &(field, value) in &[
+        ("bootstrap.manifests_path", &config.bootstrap.manifests_path),
+        ("certificates.ca_path", &config.certificates.ca_path),
+        ("secrets.path", &config.secrets.path),
+        ("etcd.config_path", &config.etcd.config_path),
+        ("audit.log_dir", &config.audit.log_dir),
+    ]
+    {
+        validation.absolute_path(field, value);
+    }
+
+    for &(field, value) in &[
+        ("kubelet.config_file", &config.kubelet.config_file),
+        ("etcd.config_file", &config.etcd.config_file),
+        ("addons.path", &config.addons.path),
+        ("manifests.override_dir", &config.manifests.override_dir),
+        ("audit.policy_file", &config.audit.policy_file),
+        ("audit.webhook_config_file", &config.audit.webhook_config_file),
+    ]
+    {
+        if let Some(ref value) = *value {
+            validation.absolute_path(field, value);
+        }
+    }
+
+    for &(field, value) in &[
+        ("audit.policy_file", &config.audit.policy_file),
+        ("audit.webhook_config_file", &config.audit.webhook_config_file),
+    ]
+    {
+        if let Some(ref value) = *value {
+            if !config.audit.enabled {
+                validation.issue(field, "has no effect unless audit.enabled is true");
+            } else if !Path::new(value).is_file() {
+                validation.issue(field, format!("{} does not exist", value));
+            }
+        }
+    }
+
+    if let Some(ref policy_file) = config.audit.policy_file {
+        if config.audit.enabled {
+            if let Ok(contents) = fs::read_to_string(policy_file) {
+                if let Some(issue) = audit_policy_issue(&contents) {
+                    validation.issue("audit.policy_file", format!("{} {}", policy_file, issue));
+                }
+            }
+        }
+    }
+
+    if let Some(ref override_dir) = config.manifests.override_dir {
+        if !Path::new(override_dir).is_dir() {
+            validation.issue(
+                "manifests.override_dir",
+                format!("{} is not a directory", override_dir),
+            );
+        }
+        for name in manifests::unknown_overrides(override_dir) {
+            validation.issue(
+                "manifests.override_dir",
+                format!(
+                    "{} does not override any built-in manifest; see kubeception manifests dump",
+                    name
+                ),
+            );
+        }
+    }
+
+    if let Err(error) = config.profile() {
+        validation.issue("system.profile", error.to_string());
+    }
+
+    validation.image("kubeception.image", &config.kubeception.image);
+
+    if !valid_kubernetes_version(&config.kubernetes.version) {
+        validation.issue(
+            "kubernetes.version",
+            format!(
+                "{} is not a valid Kubernetes version, such as v1.11.0",
+                config.kubernetes.version
+            ),
+        );
+    }
+
+    if !valid_domain(&config.dns.domain) {
+        validation.issue(
+            "dns.domain",
+            format!("{} is not a valid domain, such as cluster.local", config.dns.domain),
+        );
+    }
+
+    for server in &config.dns.upstream_servers {
+        validation.nameserver("dns.upstream_servers", server);
+    }
+
+    for (domain, servers) in &config.dns.stub_domains {
+        let field = format!("dns.stub_domains.{}", domain);
+        if !valid_domain(domain) {
+            validation.issue(&field, format!("{} is not a valid domain", domain));
+        }
+        if servers.is_empty() {
+            validation.issue(&field, "must list at least one nameserver");
+        }
+        for server in servers {
+            validation.nameserver(&field, server);
+        }
+    }
+
+    if let Some(ref oidc) = config.auth.oidc {
+        if !oidc.issuer_url.starts_with("https://") {
+            validation.issue(
+                "auth.oidc.issuer_url",
+                format!("{} must be an https:// URL", oidc.issuer_url),
+            );
+        }
+        let mut fields = vec![
+            ("auth.oidc.issuer_url", &oidc.issuer_url),
+            ("auth.oidc.client_id", &oidc.client_id),
+            ("auth.oidc.username_claim", &oidc.username_claim),
+        ];
+        if let Some(ref groups_claim) = oidc.groups_claim {
+            fields.push(("auth.oidc.groups_claim", groups_claim));
+        }
+        for (field, value) in fields {
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+                validation.issue(field, "must not be empty or contain whitespace or quotes");
+            }
+        }
+        if let Some(ref ca_file) = oidc.ca_file {
+            validation.absolute_path("auth.oidc.ca_file", ca_file);
+            if !Path::new(ca_file).is_file() {
+                validation.issue("auth.oidc.ca_file", format!("{} does not exist", ca_file));
+            }
+        }
+    }
+
+    for name in config.feature_gates.keys() {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            validation.issue(
+                "feature_gates",
+                format!("{} is not a valid feature gate name", name),
+            );
+        }
+    }
+
+    for &(section, extra_args) in &[
+        ("apiserver", &config.apiserver.extra_args),
+        ("controller_manager", &config.controller_manager.extra_args),
+        ("scheduler", &config.scheduler.extra_args),
+        ("proxy", &config.proxy.extra_args),
+        ("kubelet", &config.kubelet.extra_args),
+    ]
+    {
+        let field = format!("{}.extra_args", section);
+        for (name, value) in extra_args {
+            if name.is_empty() || name.starts_with('-') {
+                validation.issue(
+                    &field,
+                    format!("{} must be given without leading dashes, such as v = \"2\"", name),
+                );
+            }
+            if name == "feature-gates" && !config.feature_gates.is_empty() {
+                validation.issue(
+                    &field,
+                    "feature-gates conflicts with the global feature_gates table",
+                );
+            }
+            if value.contains('\n') {
+                validation.issue(&field, format!("{} must not span several lines", name));
+            }
+            if section == "kubelet" &&
+                value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+            {
+                validation.issue(
+                    &field,
+                    format!("{} must not contain whitespace or quotes", name),
+                );
+            }
+        }
+    }
+
+    for (index, patch) in config.patches.iter().enumerate() {
+        let field = format!("patches.{}", index);
+        if patch.kind.is_empty() || patch.name.is_empty() {
+            validation.issue(&field, "kind and name must be set");
+        }
+        if patch.patch.trim().is_empty() {
+            validation.issue(&field, "patch must not be empty");
+            continue;
+        }
+        if patch.patch_type == PatchType::Json {
+            let operations = serde_json::from_str::<Vec<serde_json::Value>>(&patch.patch);
+            let valid = match operations {
+                Ok(operations) => {
+                    operations.iter().all(|operation| {
+                        operation["op"].is_string() && operation["path"].is_string()
+                    })
+                }
+                Err(_) => false,
+            };
+            if !valid {
+                validation.issue(
+                    &field,
+                    "a json patch must be an array of operations with op and path",
+                );
+            }
+        }
+        if patch.patch_type == PatchType::Merge {
+            let merge_patch = serde_yaml::from_str::<serde_json::Value>(&patch.patch);
+            if !merge_patch.map(|merge_patch| merge_patch.is_object()).unwrap_or(false) {
+                validation.issue(&field, "a merge patch must be a YAML or JSON mapping");
+            }
+        }
+    }
+
+    if let Some(mtu) = config.net.flannel.mtu {
+        if mtu < 576 || mtu > 9000 {
+            validation.issue("net.flannel.mtu", format!("{} is not within 576-9000", mtu));
+        }
+    }
+
+    if config.net.flannel.vxlan_port == 0 {
+        validation.issue("net.flannel.vxlan_port", "must not be 0");
+    }
+
+    for &(field, value) in &[
+        ("net.flannel.iface", &config.net.flannel.iface),
+        ("net.flannel.iface_regex", &config.net.flannel.iface_regex),
+    ]
+    {
+        if value.as_ref().map(|value| value.is_empty()) == Some(true) {
+            validation.issue(field, "must not be empty");
+        }
+    }
+
+    if config.net.flannel.backend == FlannelBackend::Wireguard {
+        let flannel = config.images.flannel.as_ref();
+        let digest_only = flannel.map(|image| image.tag.is_none() && image.digest.is_some()) ==
+            Some(true);
+        if !digest_only {
+            let tag = flannel_tag(config);
+            if flannel_version(&tag).map_or(true, |version| version < (0, 15, 0)) {
+                validation.issue(
+                    "net.flannel.backend",
+                    format!(
+                        "the wireguard backend requires flannel v0.15.0 or later, but the flannel tag is {}; set [images.flannel] tag",
+                        tag
+                    ),
+                );
+            }
+        }
+    }
+
+    if config.net.calico.mtu < 576 || config.net.calico.mtu > 9000 {
+        validation.issue(
+            "net.calico.mtu",
+            format!("{} is not within 576-9000", config.net.calico.mtu),
+        );
+    }
+
+    if config.net.calico.ip_autodetection_method.is_empty() {
+        validation.issue("net.calico.ip_autodetection_method", "must not be empty");
+    }
+
+    if let Some(ref registry) = config.images.registry {
+        if registry.contains("://") || !valid_image_reference(&format!("{}/image", registry)) {
+            validation.issue(
+                "images.registry",
+                format!("{} is not a valid registry, such as registry.example.com:5000", registry),
+            );
+        }
+    }
+
+    if let Some(ref pull_secret) = config.images.pull_secret {
+        validation.absolute_path("images.pull_secret", pull_secret);
+    }
+
+    for component in COMPONENTS {
+        if let Some(digest) = config.images.component(component.name).and_then(
+            |image| image.digest.as_ref(),
+        )
+        {
+            if !valid_digest(digest) {
+                validation.issue(
+                    &format!("images.{}.digest", component.name),
+                    format!("{} is not a valid sha256 digest", digest),
+                );
+                continue;
+            }
+        }
+        validation.image(
+            &format!("images.{}", component.name),
+            &component.image(config),
+        );
+    }
+
+    validation.issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use toml;
+
+    #[test]
+    fn image_references() {
+        for image in &[
+            "busybox",
+            "library/busybox:1.29",
+            "quay.io/coreos/flannel:v0.10.0-amd64",
+            "localhost/kubeception",
+            "registry.example.com:5000/team/hyperkube:v1.13.1",
+            "gcr.io/google-containers/pause@sha256:59eec8837a4d942cc19a52b8c09ea75121acc38114a2c68b98983ce9356b8610",
+        ]
+        {
+            assert!(valid_image_reference(image), "{} should be valid", image);
+        }
+        for image in &[
+            "",
+            "Busybox",
+            "quay.io/",
+            "quay.io//flannel",
+            "flannel:",
+            "flannel:-v1",
+            "flannel@sha256:abc",
+            "flannel@59eec8837a4d942cc19a52b8c09ea75121acc38114a2c68b98983ce9356b8610",
+        ]
+        {
+            assert!(!valid_image_reference(image), "{} should be invalid", image);
+        }
+    }
+
+    #[test]
+    fn lines_of_fields() {
+        let source = "apiVersion = \"kubeception/v1alpha1\"\n\n[net]\ncluster_cidr_range = \"x\"\ncluster_cidr = \"10.2.0.0/16\"\n\n[net.flannel]\nbackend = \"vxlan\"\n";
+        assert_eq!(line_of(source, "apiVersion"), Some(1));
+        assert_eq!(line_of(source, "net.cluster_cidr"), Some(5));
+        assert_eq!(line_of(source, "net.flannel.backend"), Some(8));
+        assert_eq!(line_of(source, "net.backend"), None);
+        assert_eq!(line_of(source, "net.service_cluster_ip_range"), None);
+    }
+
+    #[test]
+    fn flannel_versions() {
+        assert_eq!(flannel_version("v0.15.0"), Some((0, 15, 0)));
+        assert_eq!(flannel_version("v0.10.0-amd64"), Some((0, 10, 0)));
+        assert_eq!(flannel_version("0.16.3"), Some((0, 16, 3)));
+        assert_eq!(flannel_version("latest"), None);
+        assert_eq!(flannel_version("v0.15"), None);
+    }
+
+    #[test]
+    fn audit_policies() {
+        assert_eq!(
+            audit_policy_issue("apiVersion: audit.k8s.io/v1\nkind: Policy\nrules:\n- level: Metadata\n"),
+            None
+        );
+        assert!(audit_policy_issue("apiVersion: v1\nkind: ConfigMap\n").is_some());
+        assert!(audit_policy_issue("apiVersion: audit.k8s.io/v1beta1\nkind: Policy\n").is_some());
+        assert!(audit_policy_issue("rules: [").is_some());
+    }
+
+    #[test]
+    fn wireguard_requires_a_recent_flannel() {
+        let mut config: Config = toml::from_str("[net.flannel]\nbackend = \"wireguard\"\n").unwrap();
+        assert!(validate(&config).iter().any(|issue| issue.field == "net.flannel.backend"));
+        config = toml::from_str(
+            "[net.flannel]\nbackend = \"wireguard\"\n[images.flannel]\ntag = \"v0.14.0\"\n",
+        ).unwrap();
+        assert!(validate(&config).iter().any(|issue| issue.field == "net.flannel.backend"));
+        config = toml::from_str(
+            "[net.flannel]\nbackend = \"wireguard\"\n[images.flannel]\ntag = \"v0.15.1\"\n",
+        ).unwrap();
+        assert!(!validate(&config).iter().any(|issue| issue.field == "net.flannel.backend"));
+    }
+
+    #[test]
+    fn wireguard_with_a_digest_only_warns() {
+        let config: Config = toml::from_str(&format!(
+            "[net.flannel]\nbackend = \"wireguard\"\n[images.flannel]\ndigest = \"sha256:{}\"\n",
+            "0".repeat(64)
+        )).unwrap();
+        assert!(!validate(&config).iter().any(|issue| issue.field == "net.flannel.backend"));
+        assert_eq!(warnings(&config).len(), 1);
+    }
+}
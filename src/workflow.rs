@@ -0,0 +1,187 @@
+use std;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use config::{Config, ConfigError};
+use etcd::{Etcd, EtcdError};
+use k8s::{K8s, K8sError};
+use state::{State, StateError};
+
+pub enum Step {
+    Certs,
+    Etcd,
+    PullImages,
+    BootstrapManifests,
+    ControlPlane,
+    LabelMaster,
+    PivotKubelet,
+}
+
+pub const BOOTSTRAP_STEPS: &'static [Step] = &[
+    Step::Certs,
+    Step::Etcd,
+    Step::PullImages,
+    Step::BootstrapManifests,
+    Step::ControlPlane,
+    Step::LabelMaster,
+    Step::PivotKubelet,
+];
+
+pub struct Workflow {}
+
+pub enum WorkflowError {
+    UnknownError,
+}
+
+impl From<EtcdError> for WorkflowError {
+    fn from(_error: EtcdError) -> WorkflowError {
+        WorkflowError::UnknownError
+    }
+}
+
+impl From<K8sError> for WorkflowError {
+    fn from(_error: K8sError) -> WorkflowError {
+        WorkflowError::UnknownError
+    }
+}
+
+impl From<std::io::Error> for WorkflowError {
+    fn from(_error: std::io::Error) -> WorkflowError {
+        WorkflowError::UnknownError
+    }
+}
+
+impl From<StateError> for WorkflowError {
+    fn from(_error: StateError) -> WorkflowError {
+        WorkflowError::UnknownError
+    }
+}
+
+impl From<ConfigError> for WorkflowError {
+    fn from(_error: ConfigError) -> WorkflowError {
+        WorkflowError::UnknownError
+    }
+}
+
+impl fmt::Debug for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WorkflowError")
+    }
+}
+
+pub fn failed<E>(message: &'static str) -> impl Fn(E) -> E {
+    move |error| {
+        error!("{}", message);
+        error
+    }
+}
+
+impl Step {
+    pub fn names() -> Vec<&'static str> {
+        BOOTSTRAP_STEPS.iter().map(|step| step.name()).collect()
+    }
+
+    pub fn from_name(name: &str) -> Option<&'static Step> {
+        BOOTSTRAP_STEPS.iter().find(|step| step.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Step::Certs => "certs",
+            Step::Etcd => "etcd",
+            Step::PullImages => "pull-images",
+            Step::BootstrapManifests => "bootstrap-manifests",
+            Step::ControlPlane => "control-plane",
+            Step::LabelMaster => "label-master",
+            Step::PivotKubelet => "pivot-kubelet",
+        }
+    }
+
+    fn run(&self, config: &Config) -> Result<(), WorkflowError> {
+        match *self {
+            Step::Certs => {
+                Etcd::certificates(config)?;
+                K8s::certificates(config)?;
+            }
+            Step::Etcd => Etcd::bootstrap(config)?,
+            Step::PullImages => K8s::pull_images(config)?,
+            Step::BootstrapManifests => K8s::bootstrap(config)?,
+            Step::ControlPlane => K8s::control_plane(config)?,
+            Step::LabelMaster => K8s::label_master(config)?,
+            Step::PivotKubelet => K8s::pivot_kubelet(config)?,
+        }
+        Ok(())
+    }
+}
+
+impl Workflow {
+    pub fn phase(config: &Config, step: &Step) -> Result<(), WorkflowError> {
+        let inputs = State::inputs(config);
+        let mut state = State::load(config)?;
+        if let Err(error) = step.run(config) {
+            error!("phase {} failed", step.name());
+            return Err(error);
+        }
+        state.complete(step.name(), &inputs);
+        state.save(config)?;
+        Ok(())
+    }
+
+    pub fn dry_run(config: &Config, output_dir: &Path) -> Result<(), WorkflowError> {
+        info!("rendering into {}", output_dir.display());
+        for path in &[
+            &config.secrets.path,
+            &config.etcd.config_path,
+            &config.bootstrap.manifests_path,
+        ]
+        {
+            fs::create_dir_all(config.host_path(path))?;
+        }
+        for file in &[config.kubelet_config_file()?, config.etcd_config_file()?] {
+            if let Some(parent) = config.host_path(file).parent() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        Etcd::certificates(config)?;
+        K8s::certificates(config)?;
+        Etcd::render(config)?;
+        K8s::render(config, output_dir)?;
+        Ok(())
+    }
+
+    pub fn bootstrap(
+        config: &Config,
+        resume: bool,
+        skip_phases: &[&str],
+    ) -> Result<(), WorkflowError> {
+        let inputs = State::inputs(config);
+        let mut state = if resume {
+            State::load(config)?
+        } else {
+            State::default()
+        };
+        let mut rerun = !resume;
+        for step in BOOTSTRAP_STEPS {
+            if skip_phases.contains(&step.name()) {
+                info!("phase {} skipped", step.name());
+                continue;
+            }
+            if !rerun && state.completed(step.name(), &inputs) {
+                info!("phase {} already completed, skipping", step.name());
+                continue;
+            }
+            rerun = true;
+            if let Err(error) = step.run(config) {
+                error!(
+                    "phase {} failed; run bootstrap with --resume to continue from it",
+                    step.name()
+                );
+                return Err(error);
+            }
+            state.complete(step.name(), &inputs);
+            state.save(config)?;
+        }
+        Ok(())
+    }
+}